@@ -0,0 +1,36 @@
+pub use lexer::Lexer;
+pub use parser::{Parser, ArmorMessage, MessageType, HeaderType, ParseError, ParseResult};
+pub use encoder::{encode, write_armor};
+pub use scanner::{ArmorBlock, Scanner, scan, scan_reader};
+pub use mime::{MimePart, ContentType, TransferEncoding, decode_part, decode_quoted_printable};
+pub use pgp_mime::{PgpMime, PgpMimeError, PgpMimeResult, build_encrypted, build_signed};
+pub use pgp_mime::extract as extract_pgp_mime;
+pub use mailbox::{MailSource, MailFinding, mbox_messages, scan_mbox, scan_maildir, scan_mail_store};
+pub use packet::{Packet, PacketTag, PacketFormat, PacketError, PacketResult, Packets, packets};
+pub use label::{LabelError, allowed_first_packets, check_label, check_packets};
+pub use key::{PublicKeyAlgorithm, KeyId, Fingerprint, KeyProtection, KeyInfo, KeyError, KeyResult, key_info, keys, message_keys};
+pub use user::{UserEntry, users, message_users};
+pub use signature::{HashAlgorithm, SignatureType, SignatureInfo, SignatureError, SignatureResult, CleartextMessage,
+                    signature_info, signatures, message_signatures, check_hash_headers, check_signature_block,
+                    parse_cleartext};
+pub use literal::{LiteralFormat, CompressionAlgorithm, LiteralData, LiteralError, LiteralResult, literal_data,
+                  literal_data_with_limit, message_literal_data};
+pub use zeroize::SecretBytes;
+pub use encoder::{redaction_placeholder, encode_redacted};
+pub use secrets::{SecretProtection, SecretFinding, SecretScan, scan_secrets, scan_secrets_with_limits,
+                  scan_secret_files};
+pub use redact::{Redactor, redact_secrets};
+pub use headers::{ArmorHeaders, ValidationError, ValidationResult, validate_headers, escape_key, escape_value};
+pub use crc24::{Crc24, Crc24Hasher, crc_octets};
+pub use crc24::combine as combine_crc24;
+pub use base64::{encode as encode_base64, decode as decode_base64};
+pub use body::DecodedBody;
+pub use push::{Event, PushParser};
+pub use visitor::{Visitor, Location};
+pub use validate::{Summary, validate};
+pub use limits::{Limit, Limits};
+pub use encoder::ArmorEncoder;
+#[cfg(feature = "async")]
+pub use async_armor::{ArmorReader, ArmorWriter};
+#[cfg(feature = "codec")]
+pub use codec::ArmorCodec;
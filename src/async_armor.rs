@@ -0,0 +1,326 @@
+use std::cmp;
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use futures_io::{AsyncRead, AsyncWrite};
+use encoder::ArmorEncoder;
+use parser::{MessageType, HeaderType, ParseError};
+use push::{Event, PushParser};
+use zeroize::{self, SecretBytes};
+
+
+const READ_BUFFER_LENGTH: usize = 8192;
+
+
+fn invalid_data(err: ParseError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// De-armors a block from an asynchronous reader, yielding the octets of the
+/// body as its lines arrive. The header line and armor headers are known once
+/// the first octets have been read. Armor that does not parse, or a checksum
+/// that does not match the body, is an `InvalidData` error.
+pub struct ArmorReader<R> {
+    inner: R,
+    parser: PushParser,
+    events: Vec<Event>,
+    buffer: Vec<u8>,
+    // Decoded body octets not read yet, wiped as they are dropped.
+    pieces: VecDeque<SecretBytes>,
+    position: usize,
+    message_type: Option<MessageType>,
+    header_block: Vec<(HeaderType, String)>,
+    done: bool
+}
+
+impl<R: AsyncRead + Unpin> ArmorReader<R> {
+    pub fn new(inner: R) -> ArmorReader<R> {
+        ArmorReader {
+            inner,
+            parser: PushParser::new(),
+            events: Vec::new(),
+            buffer: vec![0; READ_BUFFER_LENGTH],
+            pieces: VecDeque::new(),
+            position: 0,
+            message_type: None,
+            header_block: Vec::new(),
+            done: false
+        }
+    }
+
+    /// The type of the block, once its header line has been read.
+    pub fn message_type(&self) -> Option<&MessageType> {
+        self.message_type.as_ref()
+    }
+
+    /// The armor headers read so far.
+    pub fn header_block(&self) -> &[(HeaderType, String)] {
+        &self.header_block
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn handle_events(&mut self) -> io::Result<()> {
+        for event in self.events.drain(..) {
+            match event {
+                Event::Begin(message_type) => self.message_type = Some(message_type),
+                Event::Header(header_type, value) => self.header_block.push((header_type, value)),
+                Event::Body(octets) => self.pieces.push_back(octets),
+                Event::Checksum { valid: false, .. } => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "armor checksum does not match the body"));
+                }
+                Event::Checksum { .. } => {}
+                Event::End(_) => self.done = true
+            }
+        }
+
+        Ok(())
+    }
+
+    // Copy decoded octets out, dropping the pieces that are used up.
+    fn read_pieces(&mut self, output: &mut [u8]) -> usize {
+        let mut length = 0;
+        while length < output.len() {
+            let piece = match self.pieces.front() {
+                Some(piece) => piece,
+                None => break
+            };
+            let count = cmp::min(piece.len() - self.position, output.len() - length);
+            output[length..length + count].copy_from_slice(&piece[self.position..self.position + count]);
+            length += count;
+            self.position += count;
+            if self.position == piece.len() {
+                self.pieces.pop_front();
+                self.position = 0;
+            }
+        }
+
+        length
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for ArmorReader<R> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, output: &mut [u8]) -> Poll<io::Result<usize>> {
+        let reader = &mut *self;
+        loop {
+            if !reader.pieces.is_empty() || output.is_empty() {
+                return Poll::Ready(Ok(reader.read_pieces(output)));
+            }
+            if reader.done {
+                return Poll::Ready(Ok(0));
+            }
+
+            let length = match Pin::new(&mut reader.inner).poll_read(cx, &mut reader.buffer) {
+                Poll::Ready(Ok(length)) => length,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending
+            };
+            let result = if length == 0 {
+                reader.parser.finish(&mut reader.events)
+            } else {
+                reader.parser.feed(&reader.buffer[..length], &mut reader.events)
+            };
+            zeroize::zeroize(&mut reader.buffer[..length]);
+
+            result.map_err(invalid_data)?;
+            reader.handle_events()?;
+        }
+    }
+}
+
+/// Armors the octets written to it into an asynchronous writer. The header
+/// line and armor headers are written with the first body line, and closing
+/// the writer writes the checksum and tail line before closing the inner
+/// writer.
+pub struct ArmorWriter<W> {
+    inner: W,
+    encoder: Option<ArmorEncoder>,
+    output: Vec<u8>,
+    position: usize
+}
+
+impl<W: AsyncWrite + Unpin> ArmorWriter<W> {
+    pub fn new(inner: W, message_type: &MessageType, header_block: &[(HeaderType, String)]) -> ArmorWriter<W> {
+        ArmorWriter {
+            inner,
+            encoder: Some(ArmorEncoder::new(message_type, header_block)),
+            output: Vec::new(),
+            position: 0
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    // Write out the armor encoded so far.
+    fn poll_drain(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        while self.position < self.output.len() {
+            match Pin::new(&mut self.inner).poll_write(cx, &self.output[self.position..]) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Poll::Ready(Ok(length)) => self.position += length,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending
+            }
+        }
+        zeroize::zeroize(&mut self.output);
+        self.output.clear();
+        self.position = 0;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for ArmorWriter<W> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context, octets: &[u8]) -> Poll<io::Result<usize>> {
+        let writer = &mut *self;
+        // The armor of earlier writes is held until the inner writer takes
+        // it, so a slow inner writer holds back this one.
+        match writer.poll_drain(cx) {
+            Poll::Ready(Ok(())) => {}
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+            Poll::Pending => return Poll::Pending
+        }
+
+        match writer.encoder {
+            Some(ref mut encoder) => {
+                encoder.update(octets, &mut writer.output);
+                Poll::Ready(Ok(octets.len()))
+            }
+            None => Poll::Ready(Err(io::Error::new(io::ErrorKind::Other, "armor writer is closed")))
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let writer = &mut *self;
+        match writer.poll_drain(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut writer.inner).poll_flush(cx),
+            poll => poll
+        }
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let writer = &mut *self;
+        if let Some(encoder) = writer.encoder.take() {
+            encoder.finish(&mut writer.output);
+        }
+        match writer.poll_drain(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut writer.inner).poll_close(cx),
+            poll => poll
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use futures::executor::block_on;
+    use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+    use lexer::Lexer;
+    use parser::Parser;
+    use super::{ArmorReader, ArmorWriter};
+
+
+    // An in-memory stream that moves a few bytes at a time, and is not ready
+    // every other time it is polled.
+    struct Trickle {
+        data: Vec<u8>,
+        position: usize,
+        ready: bool
+    }
+
+    impl Trickle {
+        fn new(data: &[u8]) -> Trickle {
+            Trickle { data: data.to_vec(), position: 0, ready: false }
+        }
+
+        fn poll_ready(&mut self, cx: &mut Context) -> bool {
+            self.ready = !self.ready;
+            if !self.ready {
+                cx.waker().wake_by_ref();
+            }
+            self.ready
+        }
+    }
+
+    impl AsyncRead for Trickle {
+        fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, output: &mut [u8]) -> Poll<io::Result<usize>> {
+            if !self.poll_ready(cx) {
+                return Poll::Pending;
+            }
+            let length = ::std::cmp::min(::std::cmp::min(7, output.len()), self.data.len() - self.position);
+            output[..length].copy_from_slice(&self.data[self.position..self.position + length]);
+            self.position += length;
+
+            Poll::Ready(Ok(length))
+        }
+    }
+
+    impl AsyncWrite for Trickle {
+        fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context, octets: &[u8]) -> Poll<io::Result<usize>> {
+            if !self.poll_ready(cx) {
+                return Poll::Pending;
+            }
+            let length = ::std::cmp::min(5, octets.len());
+            self.data.extend_from_slice(&octets[..length]);
+
+            Poll::Ready(Ok(length))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn test_armor_reader() {
+        for text in [include_str!("../sample/pub.asc"), include_str!("../sample/priv.asc")].iter() {
+            let message = Parser::new(Lexer::new(text)).parse().unwrap();
+            let mut reader = ArmorReader::new(Trickle::new(text.as_bytes()));
+            let mut body = Vec::new();
+            block_on(reader.read_to_end(&mut body)).unwrap();
+
+            assert_eq!(body, message.decode_body().unwrap());
+            assert_eq!(reader.message_type(), Some(message.header_type()));
+            assert_eq!(reader.header_block(), message.header_block());
+        }
+    }
+
+    #[test]
+    fn test_armor_reader_errors() {
+        let text = include_str!("../sample/message.asc").replace("=njUN", "=njUM");
+        let mut reader = ArmorReader::new(Trickle::new(text.as_bytes()));
+        let err = block_on(reader.read_to_end(&mut Vec::new())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let text = include_str!("../sample/message.asc").replace("-----END PGP MESSAGE-----", "");
+        let mut reader = ArmorReader::new(Trickle::new(text.as_bytes()));
+        let err = block_on(reader.read_to_end(&mut Vec::new())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_armor_writer() {
+        let text = include_str!("../sample/pub.asc");
+        let message = Parser::new(Lexer::new(text)).parse().unwrap();
+        let body = message.decode_body().unwrap();
+
+        let mut writer = ArmorWriter::new(Trickle::new(&[]), message.header_type(), message.header_block());
+        for chunk in body.chunks(100) {
+            block_on(writer.write_all(chunk)).unwrap();
+        }
+        block_on(writer.close()).unwrap();
+
+        assert_eq!(writer.into_inner().data, ::encoder::encode(&message).into_bytes());
+    }
+}
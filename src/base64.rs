@@ -1,4 +1,7 @@
 #![allow(dead_code)]
+#[cfg(feature = "simd")]
+use base64_simd;
+
 pub type Octet = u32;
 // Mask for keeping octets as 3 bytes.
 const OCTET_MASK: u32 = 0x00FF_FFFF;
@@ -7,6 +10,25 @@ pub type Sextet = u32;
 // Mask for keeping sextets as 6 bits.
 const SEXTET_MASK: u32 = 0x3F;
 
+// Marks the bytes outside the base64 alphabet in the decode table.
+const INVALID_SEXTET: u8 = 0xFF;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// The sextet value of every byte of the base64 alphabet.
+const DECODE_TABLE: [u8; 256] = decode_table();
+
+const fn decode_table() -> [u8; 256] {
+    let mut table = [INVALID_SEXTET; 256];
+    let mut i = 0;
+    while i < ALPHABET.len() {
+        table[ALPHABET[i] as usize] = i as u8;
+        i += 1;
+    }
+
+    table
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Base64 {
     data: Vec<Sextet>
@@ -67,6 +89,154 @@ pub fn is_base64(bytes: &[u8]) -> bool {
     true
 }
 
+/// Tests whether a character belongs to the base64 alphabet. The pad
+/// character is not part of the alphabet.
+pub fn is_base64_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '+' || ch == '/'
+}
+
+/// Tests whether a byte belongs to the base64 alphabet. The pad character is
+/// not part of the alphabet.
+#[inline]
+pub fn is_base64_byte(byte: u8) -> bool {
+    DECODE_TABLE[byte as usize] != INVALID_SEXTET
+}
+
+/// Tests whether a string consists only of base64 alphabet characters.
+pub fn is_base64_str(string: &str) -> bool {
+    string.chars().all(is_base64_char)
+}
+
+/// Maps a character of the base64 alphabet to its sextet value.
+#[inline]
+pub fn decode_char(byte: u8) -> Option<Sextet> {
+    match DECODE_TABLE[byte as usize] {
+        INVALID_SEXTET => None,
+        sextet => Some(sextet as Sextet)
+    }
+}
+
+/// Decodes padded base64 text into octets. Returns `None` when the text is
+/// not valid base64.
+pub fn decode(text: &str) -> Option<Vec<u8>> {
+    let mut result = Vec::with_capacity(text.len() / 4 * 3);
+    decode_into(text, &mut result)?;
+
+    Some(result)
+}
+
+/// Decodes padded base64 text, appending the octets to `result`. On failure
+/// `result` holds the octets of the groups decoded before the error.
+pub fn decode_into(text: &str, result: &mut Vec<u8>) -> Option<()> {
+    decode_bytes_into(text.as_bytes(), result)
+}
+
+/// Decodes padded base64 characters given as bytes, appending the octets to
+/// `result`. On failure `result` holds the octets of the groups decoded before
+/// the error.
+pub fn decode_bytes_into(bytes: &[u8], result: &mut Vec<u8>) -> Option<()> {
+    if bytes.len() % 4 != 0 {
+        return None;
+    }
+
+    // The vectorized decoder takes whole blocks up to the first padding or
+    // invalid character, and leaves the rest to the scalar loop.
+    let consumed = simd_decode(bytes, result);
+    decode_groups(&bytes[consumed..], result)
+}
+
+// Decode the text one group of four characters at a time.
+fn decode_groups(bytes: &[u8], result: &mut Vec<u8>) -> Option<()> {
+    let groups = bytes.len() / 4;
+    for (i, group) in bytes.chunks(4).enumerate() {
+        // Padding may only appear at the end of the final group.
+        let padding = group.iter().rev().take_while(|&&byte| byte == b'=').count();
+        if padding > 2 || (padding > 0 && i + 1 != groups) {
+            return None;
+        }
+
+        let mut octet: Octet = 0;
+        for &byte in &group[..4 - padding] {
+            octet = (octet << 6) | decode_char(byte)?;
+        }
+        octet = (octet << (6 * padding)) & OCTET_MASK;
+
+        for j in 0..(3 - padding) {
+            result.push((octet >> (16 - 8 * j)) as u8);
+        }
+    }
+
+    Some(())
+}
+
+#[cfg(feature = "simd")]
+fn simd_decode(bytes: &[u8], result: &mut Vec<u8>) -> usize {
+    base64_simd::decode_blocks(bytes, result)
+}
+
+#[cfg(not(feature = "simd"))]
+fn simd_decode(_bytes: &[u8], _result: &mut Vec<u8>) -> usize {
+    0
+}
+
+#[cfg(feature = "simd")]
+fn simd_encode(octets: &[u8], result: &mut Vec<u8>) -> usize {
+    base64_simd::encode_blocks(octets, result)
+}
+
+#[cfg(not(feature = "simd"))]
+fn simd_encode(_octets: &[u8], _result: &mut Vec<u8>) -> usize {
+    0
+}
+
+/// Encodes octets as padded base64 text, without line breaks.
+pub fn encode(octets: &[u8]) -> String {
+    let mut result = Vec::with_capacity((octets.len() + 2) / 3 * 4);
+    let consumed = simd_encode(octets, &mut result);
+    encode_groups(&octets[consumed..], &mut result);
+
+    // Every byte written is from the base64 alphabet or the pad character.
+    String::from_utf8(result).expect("base64 output is ASCII")
+}
+
+// Encode octets one group of three at a time, padding the final group.
+fn encode_groups(octets: &[u8], result: &mut Vec<u8>) {
+    for group in octets.chunks(3) {
+        let mut octet: Octet = 0;
+        for (i, &byte) in group.iter().enumerate() {
+            octet |= (byte as Octet) << (16 - 8 * i);
+        }
+
+        for i in 0..4 {
+            if i <= group.len() {
+                result.push(ALPHABET[((octet >> (18 - 6 * i)) & SEXTET_MASK) as usize]);
+            } else {
+                result.push(b'=');
+            }
+        }
+    }
+}
+
+/// Encodes octets with the scalar code alone.
+pub fn encode_scalar(octets: &[u8]) -> String {
+    let mut result = Vec::with_capacity((octets.len() + 2) / 3 * 4);
+    encode_groups(octets, &mut result);
+
+    String::from_utf8(result).expect("base64 output is ASCII")
+}
+
+/// Decodes padded base64 text with the scalar code alone.
+pub fn decode_scalar(text: &str) -> Option<Vec<u8>> {
+    let bytes = text.as_bytes();
+    if bytes.len() % 4 != 0 {
+        return None;
+    }
+
+    let mut result = Vec::with_capacity(bytes.len() / 4 * 3);
+    decode_groups(bytes, &mut result)?;
+
+    Some(result)
+}
 
 #[cfg(test)]
 mod tests {
@@ -99,4 +269,70 @@ mod tests {
     fn test_base64() {
         run_tests(&base64_test_cases());
     }
+
+    struct DecodeTestCase {
+        text: &'static str,
+        octets: Option<Vec<u8>>
+    }
+
+    fn decode_test_cases() -> Vec<DecodeTestCase> {
+        vec![
+            DecodeTestCase { text: "", octets: Some(vec![]) },
+            DecodeTestCase { text: "Zg==", octets: Some(b"f".to_vec()) },
+            DecodeTestCase { text: "Zm8=", octets: Some(b"fo".to_vec()) },
+            DecodeTestCase { text: "Zm9v", octets: Some(b"foo".to_vec()) },
+            DecodeTestCase { text: "Zm9vYg==", octets: Some(b"foob".to_vec()) },
+            DecodeTestCase { text: "Zm9vYmFy", octets: Some(b"foobar".to_vec()) },
+            DecodeTestCase { text: "FPucA9l+", octets: Some(vec![0x14, 0xFB, 0x9C, 0x03, 0xD9, 0x7E]) },
+            DecodeTestCase { text: "Zm9", octets: None },
+            DecodeTestCase { text: "Zg==Zm9v", octets: None },
+            DecodeTestCase { text: "Z===", octets: None },
+            DecodeTestCase { text: "Zm 9", octets: None }
+        ]
+    }
+
+    #[test]
+    fn test_decode_table() {
+        for byte in 0..=255u8 {
+            assert_eq!(super::is_base64_byte(byte), super::is_base64_char(byte as char));
+        }
+        assert_eq!(super::decode_char(b'A'), Some(0));
+        assert_eq!(super::decode_char(b'/'), Some(63));
+        assert_eq!(super::decode_char(b'='), None);
+    }
+
+    #[test]
+    fn test_decode() {
+        for test_case in decode_test_cases().iter() {
+            assert_eq!(super::decode(test_case.text), test_case.octets);
+            assert_eq!(super::decode_scalar(test_case.text), test_case.octets);
+        }
+    }
+
+    #[test]
+    fn test_encode() {
+        for test_case in decode_test_cases().iter() {
+            if let Some(ref octets) = test_case.octets {
+                assert_eq!(super::encode(octets), test_case.text);
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_decode_match_scalar() {
+        for length in 0..300 {
+            let octets: Vec<u8> = (0..length).map(|i| (i * 7 + length) as u8).collect();
+            let text = super::encode(&octets);
+            assert_eq!(text, super::encode_scalar(&octets));
+            assert_eq!(super::decode(&text), Some(octets));
+
+            // Break the text in the middle, and in the padding.
+            for &position in [text.len() / 2, text.len().saturating_sub(1)].iter() {
+                if let Some(byte) = text.as_bytes().get(position) {
+                    let broken = text.replacen(*byte as char, "*", 1);
+                    assert_eq!(super::decode(&broken), super::decode_scalar(&broken));
+                }
+            }
+        }
+    }
 }
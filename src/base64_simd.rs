@@ -0,0 +1,408 @@
+// Vectorized base64 for the bulk of a body. The functions here only handle
+// whole blocks of alphabet characters; padding, invalid input and the tail of
+// the data are left to the scalar code in the base64 module, which keeps the
+// results identical to it.
+//
+// The SSSE3 and AVX2 code follows the approach of Wojciech Muła and Alfred
+// Klomp's base64 library: nibble lookup tables to validate and translate the
+// characters, and multiply-add instructions to pack the sextets.
+#![allow(dead_code)]
+
+
+/// Decode as many whole blocks of base64 characters as possible, appending the
+/// octets to `output`. Returns the number of characters consumed, which is a
+/// multiple of four. Decoding stops before the first block holding a character
+/// outside the alphabet, padding included.
+pub fn decode_blocks(input: &[u8], output: &mut Vec<u8>) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { x86::decode_avx2(input, output) };
+        }
+        if is_x86_feature_detected!("ssse3") {
+            return unsafe { x86::decode_ssse3(input, output) };
+        }
+    }
+    #[cfg(all(target_arch = "aarch64", feature = "neon"))]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return unsafe { neon::decode_neon(input, output) };
+        }
+    }
+
+    0
+}
+
+/// Encode as many whole blocks of octets as possible, appending the base64
+/// characters to `output`. Returns the number of octets consumed, which is a
+/// multiple of three.
+pub fn encode_blocks(input: &[u8], output: &mut Vec<u8>) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { x86::encode_avx2(input, output) };
+        }
+        if is_x86_feature_detected!("ssse3") {
+            return unsafe { x86::encode_ssse3(input, output) };
+        }
+    }
+    #[cfg(all(target_arch = "aarch64", feature = "neon"))]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return unsafe { neon::encode_neon(input, output) };
+        }
+    }
+
+    0
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+
+    // Validate a vector of characters and translate them to sextets. Returns
+    // `None` if any character is outside the alphabet.
+    #[target_feature(enable = "ssse3")]
+    unsafe fn translate_ssse3(chars: __m128i) -> Option<__m128i> {
+        let lut_lo = _mm_setr_epi8(0x15, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11,
+                                   0x11, 0x11, 0x13, 0x1A, 0x1B, 0x1B, 0x1B, 0x1A);
+        let lut_hi = _mm_setr_epi8(0x10, 0x10, 0x01, 0x02, 0x04, 0x08, 0x04, 0x08,
+                                   0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10);
+        let lut_roll = _mm_setr_epi8(0, 16, 19, 4, -65, -65, -71, -71, 0, 0, 0, 0, 0, 0, 0, 0);
+
+        let hi_nibbles = _mm_and_si128(_mm_srli_epi32(chars, 4), _mm_set1_epi8(0x0F));
+        let lo_nibbles = _mm_and_si128(chars, _mm_set1_epi8(0x0F));
+        let lo = _mm_shuffle_epi8(lut_lo, lo_nibbles);
+        let hi = _mm_shuffle_epi8(lut_hi, hi_nibbles);
+        if _mm_movemask_epi8(_mm_cmpgt_epi8(_mm_and_si128(lo, hi), _mm_setzero_si128())) != 0 {
+            return None;
+        }
+
+        let slashes = _mm_cmpeq_epi8(chars, _mm_set1_epi8(b'/' as i8));
+        let roll = _mm_shuffle_epi8(lut_roll, _mm_add_epi8(slashes, hi_nibbles));
+
+        Some(_mm_add_epi8(chars, roll))
+    }
+
+    // Pack four sextets per 32 bit lane into three octets at the front of the
+    // lane, in big endian order.
+    #[target_feature(enable = "ssse3")]
+    unsafe fn pack_ssse3(sextets: __m128i) -> __m128i {
+        let pairs = _mm_maddubs_epi16(sextets, _mm_set1_epi32(0x0140_0140));
+        let groups = _mm_madd_epi16(pairs, _mm_set1_epi32(0x0001_1000));
+
+        _mm_shuffle_epi8(groups, _mm_setr_epi8(2, 1, 0, 6, 5, 4, 10, 9, 8, 14, 13, 12, -1, -1, -1, -1))
+    }
+
+    #[target_feature(enable = "ssse3")]
+    pub unsafe fn decode_ssse3(input: &[u8], output: &mut Vec<u8>) -> usize {
+        let mut position = 0;
+        let mut block = [0u8; 16];
+        while input.len() - position >= 16 {
+            let chars = _mm_loadu_si128(input.as_ptr().add(position) as *const __m128i);
+            let sextets = match translate_ssse3(chars) {
+                Some(sextets) => sextets,
+                None => break
+            };
+            _mm_storeu_si128(block.as_mut_ptr() as *mut __m128i, pack_ssse3(sextets));
+            output.extend_from_slice(&block[..12]);
+            position += 16;
+        }
+
+        position
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn translate_avx2(chars: __m256i) -> Option<__m256i> {
+        let lut_lo = _mm256_setr_epi8(0x15, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11,
+                                      0x11, 0x11, 0x13, 0x1A, 0x1B, 0x1B, 0x1B, 0x1A,
+                                      0x15, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11,
+                                      0x11, 0x11, 0x13, 0x1A, 0x1B, 0x1B, 0x1B, 0x1A);
+        let lut_hi = _mm256_setr_epi8(0x10, 0x10, 0x01, 0x02, 0x04, 0x08, 0x04, 0x08,
+                                      0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10,
+                                      0x10, 0x10, 0x01, 0x02, 0x04, 0x08, 0x04, 0x08,
+                                      0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10);
+        let lut_roll = _mm256_setr_epi8(0, 16, 19, 4, -65, -65, -71, -71, 0, 0, 0, 0, 0, 0, 0, 0,
+                                        0, 16, 19, 4, -65, -65, -71, -71, 0, 0, 0, 0, 0, 0, 0, 0);
+
+        let hi_nibbles = _mm256_and_si256(_mm256_srli_epi32(chars, 4), _mm256_set1_epi8(0x0F));
+        let lo_nibbles = _mm256_and_si256(chars, _mm256_set1_epi8(0x0F));
+        let lo = _mm256_shuffle_epi8(lut_lo, lo_nibbles);
+        let hi = _mm256_shuffle_epi8(lut_hi, hi_nibbles);
+        if _mm256_testz_si256(lo, hi) == 0 {
+            return None;
+        }
+
+        let slashes = _mm256_cmpeq_epi8(chars, _mm256_set1_epi8(b'/' as i8));
+        let roll = _mm256_shuffle_epi8(lut_roll, _mm256_add_epi8(slashes, hi_nibbles));
+
+        Some(_mm256_add_epi8(chars, roll))
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn decode_avx2(input: &[u8], output: &mut Vec<u8>) -> usize {
+        let mut position = 0;
+        let mut block = [0u8; 32];
+        while input.len() - position >= 32 {
+            let chars = _mm256_loadu_si256(input.as_ptr().add(position) as *const __m256i);
+            let sextets = match translate_avx2(chars) {
+                Some(sextets) => sextets,
+                None => break
+            };
+
+            let pairs = _mm256_maddubs_epi16(sextets, _mm256_set1_epi32(0x0140_0140));
+            let groups = _mm256_madd_epi16(pairs, _mm256_set1_epi32(0x0001_1000));
+            let lanes = _mm256_shuffle_epi8(groups, _mm256_setr_epi8(
+                2, 1, 0, 6, 5, 4, 10, 9, 8, 14, 13, 12, -1, -1, -1, -1,
+                2, 1, 0, 6, 5, 4, 10, 9, 8, 14, 13, 12, -1, -1, -1, -1
+            ));
+            // Each lane holds 12 octets; move them next to each other.
+            let octets = _mm256_permutevar8x32_epi32(lanes, _mm256_setr_epi32(0, 1, 2, 4, 5, 6, 3, 7));
+            _mm256_storeu_si256(block.as_mut_ptr() as *mut __m256i, octets);
+            output.extend_from_slice(&block[..24]);
+            position += 32;
+        }
+
+        position
+    }
+
+    // Spread three octets over the four bytes of each 32 bit lane, six bits
+    // per byte.
+    #[target_feature(enable = "ssse3")]
+    unsafe fn unpack_ssse3(octets: __m128i) -> __m128i {
+        let spread = _mm_shuffle_epi8(octets, _mm_set_epi8(10, 11, 9, 10, 7, 8, 6, 7, 4, 5, 3, 4, 1, 2, 0, 1));
+        let t0 = _mm_and_si128(spread, _mm_set1_epi32(0x0FC0_FC00));
+        let t1 = _mm_mulhi_epu16(t0, _mm_set1_epi32(0x0400_0040));
+        let t2 = _mm_and_si128(spread, _mm_set1_epi32(0x003F_03F0));
+        let t3 = _mm_mullo_epi16(t2, _mm_set1_epi32(0x0100_0010));
+
+        _mm_or_si128(t1, t3)
+    }
+
+    // Translate sextets to characters of the alphabet, using the offset of the
+    // range each sextet falls in.
+    #[target_feature(enable = "ssse3")]
+    unsafe fn alphabet_ssse3(sextets: __m128i) -> __m128i {
+        let offsets = _mm_setr_epi8(65, 71, -4, -4, -4, -4, -4, -4, -4, -4, -4, -4, -19, -16, 0, 0);
+        let ranges = _mm_subs_epu8(sextets, _mm_set1_epi8(51));
+        let letters = _mm_cmpgt_epi8(sextets, _mm_set1_epi8(25));
+        let indices = _mm_sub_epi8(ranges, letters);
+
+        _mm_add_epi8(sextets, _mm_shuffle_epi8(offsets, indices))
+    }
+
+    #[target_feature(enable = "ssse3")]
+    pub unsafe fn encode_ssse3(input: &[u8], output: &mut Vec<u8>) -> usize {
+        let mut position = 0;
+        let mut block = [0u8; 16];
+        // Sixteen octets are loaded for every twelve encoded.
+        while input.len() - position >= 16 {
+            let octets = _mm_loadu_si128(input.as_ptr().add(position) as *const __m128i);
+            let chars = alphabet_ssse3(unpack_ssse3(octets));
+            _mm_storeu_si128(block.as_mut_ptr() as *mut __m128i, chars);
+            output.extend_from_slice(&block);
+            position += 12;
+        }
+
+        position
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn encode_avx2(input: &[u8], output: &mut Vec<u8>) -> usize {
+        let spread_mask = _mm256_set_epi8(10, 11, 9, 10, 7, 8, 6, 7, 4, 5, 3, 4, 1, 2, 0, 1,
+                                          10, 11, 9, 10, 7, 8, 6, 7, 4, 5, 3, 4, 1, 2, 0, 1);
+        let offsets = _mm256_setr_epi8(65, 71, -4, -4, -4, -4, -4, -4, -4, -4, -4, -4, -19, -16, 0, 0,
+                                       65, 71, -4, -4, -4, -4, -4, -4, -4, -4, -4, -4, -19, -16, 0, 0);
+        let mut position = 0;
+        let mut block = [0u8; 32];
+        // Each lane takes twelve octets from a sixteen octet load, so 28 octets
+        // are read for every 24 encoded.
+        while input.len() - position >= 28 {
+            let low = _mm_loadu_si128(input.as_ptr().add(position) as *const __m128i);
+            let high = _mm_loadu_si128(input.as_ptr().add(position + 12) as *const __m128i);
+            let octets = _mm256_inserti128_si256(_mm256_castsi128_si256(low), high, 1);
+
+            let spread = _mm256_shuffle_epi8(octets, spread_mask);
+            let t0 = _mm256_and_si256(spread, _mm256_set1_epi32(0x0FC0_FC00));
+            let t1 = _mm256_mulhi_epu16(t0, _mm256_set1_epi32(0x0400_0040));
+            let t2 = _mm256_and_si256(spread, _mm256_set1_epi32(0x003F_03F0));
+            let t3 = _mm256_mullo_epi16(t2, _mm256_set1_epi32(0x0100_0010));
+            let sextets = _mm256_or_si256(t1, t3);
+
+            let ranges = _mm256_subs_epu8(sextets, _mm256_set1_epi8(51));
+            let letters = _mm256_cmpgt_epi8(sextets, _mm256_set1_epi8(25));
+            let indices = _mm256_sub_epi8(ranges, letters);
+            let chars = _mm256_add_epi8(sextets, _mm256_shuffle_epi8(offsets, indices));
+
+            _mm256_storeu_si256(block.as_mut_ptr() as *mut __m256i, chars);
+            output.extend_from_slice(&block);
+            position += 24;
+        }
+
+        position
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", feature = "neon"))]
+mod neon {
+    use std::arch::aarch64::*;
+
+
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+
+    // Translate characters to sextets, along with a mask of the characters
+    // that belong to the alphabet.
+    #[target_feature(enable = "neon")]
+    unsafe fn translate_neon(chars: uint8x16_t) -> (uint8x16_t, uint8x16_t) {
+        let upper = vandq_u8(vcgeq_u8(chars, vdupq_n_u8(b'A')), vcleq_u8(chars, vdupq_n_u8(b'Z')));
+        let lower = vandq_u8(vcgeq_u8(chars, vdupq_n_u8(b'a')), vcleq_u8(chars, vdupq_n_u8(b'z')));
+        let digit = vandq_u8(vcgeq_u8(chars, vdupq_n_u8(b'0')), vcleq_u8(chars, vdupq_n_u8(b'9')));
+        let plus = vceqq_u8(chars, vdupq_n_u8(b'+'));
+        let slash = vceqq_u8(chars, vdupq_n_u8(b'/'));
+
+        let symbols = vbslq_u8(plus, vdupq_n_u8(62), vandq_u8(slash, vdupq_n_u8(63)));
+        let sextets = vbslq_u8(upper, vsubq_u8(chars, vdupq_n_u8(65)),
+                      vbslq_u8(lower, vsubq_u8(chars, vdupq_n_u8(71)),
+                      vbslq_u8(digit, vaddq_u8(chars, vdupq_n_u8(4)), symbols)));
+        let valid = vorrq_u8(vorrq_u8(upper, lower), vorrq_u8(digit, vorrq_u8(plus, slash)));
+
+        (sextets, valid)
+    }
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn decode_neon(input: &[u8], output: &mut Vec<u8>) -> usize {
+        let mut position = 0;
+        let mut block = [0u8; 48];
+        while input.len() - position >= 64 {
+            // The load splits the characters of every group of four over a, b,
+            // c and d.
+            let chars = vld4q_u8(input.as_ptr().add(position));
+            let (a, valid_a) = translate_neon(chars.0);
+            let (b, valid_b) = translate_neon(chars.1);
+            let (c, valid_c) = translate_neon(chars.2);
+            let (d, valid_d) = translate_neon(chars.3);
+            let valid = vandq_u8(vandq_u8(valid_a, valid_b), vandq_u8(valid_c, valid_d));
+            if vminvq_u8(valid) != 0xFF {
+                break;
+            }
+
+            let octets = uint8x16x3_t(
+                vorrq_u8(vshlq_n_u8::<2>(a), vshrq_n_u8::<4>(b)),
+                vorrq_u8(vshlq_n_u8::<4>(b), vshrq_n_u8::<2>(c)),
+                vorrq_u8(vshlq_n_u8::<6>(c), d)
+            );
+            vst3q_u8(block.as_mut_ptr(), octets);
+            output.extend_from_slice(&block);
+            position += 64;
+        }
+
+        position
+    }
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn encode_neon(input: &[u8], output: &mut Vec<u8>) -> usize {
+        let alphabet = uint8x16x4_t(
+            vld1q_u8(ALPHABET.as_ptr()),
+            vld1q_u8(ALPHABET.as_ptr().add(16)),
+            vld1q_u8(ALPHABET.as_ptr().add(32)),
+            vld1q_u8(ALPHABET.as_ptr().add(48))
+        );
+        let mask = vdupq_n_u8(0x3F);
+        let mut position = 0;
+        let mut block = [0u8; 64];
+        while input.len() - position >= 48 {
+            let octets = vld3q_u8(input.as_ptr().add(position));
+            let a = vshrq_n_u8::<2>(octets.0);
+            let b = vandq_u8(vorrq_u8(vshlq_n_u8::<4>(octets.0), vshrq_n_u8::<4>(octets.1)), mask);
+            let c = vandq_u8(vorrq_u8(vshlq_n_u8::<2>(octets.1), vshrq_n_u8::<6>(octets.2)), mask);
+            let d = vandq_u8(octets.2, mask);
+
+            let chars = uint8x16x4_t(
+                vqtbl4q_u8(alphabet, a),
+                vqtbl4q_u8(alphabet, b),
+                vqtbl4q_u8(alphabet, c),
+                vqtbl4q_u8(alphabet, d)
+            );
+            vst4q_u8(block.as_mut_ptr(), chars);
+            output.extend_from_slice(&block);
+            position += 48;
+        }
+
+        position
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    type Codec = unsafe fn(&[u8], &mut Vec<u8>) -> usize;
+
+
+    fn octets(length: usize) -> Vec<u8> {
+        (0..length).map(|i| (i * 131 + i / 7) as u8).collect()
+    }
+
+    // The implementations the CPU running the tests supports.
+    fn codecs() -> Vec<(Codec, Codec)> {
+        let mut codecs: Vec<(Codec, Codec)> = Vec::new();
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("ssse3") {
+                codecs.push((super::x86::decode_ssse3, super::x86::encode_ssse3));
+            }
+            if is_x86_feature_detected!("avx2") {
+                codecs.push((super::x86::decode_avx2, super::x86::encode_avx2));
+            }
+        }
+        #[cfg(all(target_arch = "aarch64", feature = "neon"))]
+        {
+            if std::arch::is_aarch64_feature_detected!("neon") {
+                codecs.push((super::neon::decode_neon, super::neon::encode_neon));
+            }
+        }
+
+        codecs
+    }
+
+    #[test]
+    fn test_simd_matches_scalar() {
+        for (decode, encode) in codecs() {
+            for length in (0..200).chain(Some(3000)) {
+                let octets = octets(length);
+                let text = ::base64::encode_scalar(&octets);
+
+                let mut encoded = Vec::new();
+                let consumed = unsafe { encode(&octets, &mut encoded) };
+                assert_eq!(consumed % 3, 0);
+                assert!(length < 64 || consumed > 0);
+                assert_eq!(&encoded[..], &text.as_bytes()[..consumed / 3 * 4]);
+
+                let mut decoded = Vec::new();
+                let consumed = unsafe { decode(text.as_bytes(), &mut decoded) };
+                assert_eq!(consumed % 4, 0);
+                assert!(length < 64 || consumed > 0);
+                assert_eq!(&decoded[..], &octets[..consumed / 4 * 3]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_simd_stops_at_invalid_characters() {
+        let text = ::base64::encode_scalar(&octets(300));
+        for (decode, _) in codecs() {
+            for &invalid in [b'=', b'-', b'\n', b'@', b'[', b'`', b'{', b':', 0x80, 0xFF].iter() {
+                for &position in [0, 5, 17, 63, 150, 399].iter() {
+                    let mut bytes = text.clone().into_bytes();
+                    bytes[position] = invalid;
+
+                    let mut decoded = Vec::new();
+                    let consumed = unsafe { decode(&bytes, &mut decoded) };
+                    assert!(consumed <= position);
+                    assert_eq!(&decoded[..], &octets(300)[..consumed / 4 * 3]);
+                }
+            }
+        }
+    }
+}
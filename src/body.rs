@@ -0,0 +1,322 @@
+use std::cmp;
+use std::panic;
+use std::thread;
+use base64;
+use crc24::{self, Crc24, Crc24Hasher};
+use parser::{ParseError, ParseResult};
+use zeroize::{self, SecretBytes};
+
+
+// Bodies are only split into parts of at least this many bytes, so small
+// bodies are decoded on the calling thread.
+const MIN_CHUNK_LENGTH: usize = 1 << 20;
+
+
+/// The octets of a decoded armor body and their CRC-24. The body may hold
+/// secret key material, so the octets are wiped when dropped and left out of
+/// `Debug` output.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DecodedBody {
+    octets: SecretBytes,
+    checksum: Crc24
+}
+
+impl DecodedBody {
+    fn new(octets: Vec<u8>, checksum: Crc24) -> DecodedBody {
+        DecodedBody {
+            octets: SecretBytes::new(octets),
+            checksum
+        }
+    }
+
+    pub fn octets(&self) -> &[u8] {
+        &self.octets
+    }
+
+    /// The CRC-24 of the octets, to compare with the armor checksum.
+    pub fn checksum(&self) -> Crc24 {
+        self.checksum
+    }
+
+    pub fn into_octets(self) -> SecretBytes {
+        self.octets
+    }
+}
+
+// A part of a body that starts at a line boundary and on a group boundary.
+#[derive(Copy, Clone, Debug)]
+struct Chunk {
+    start: usize,
+    end: usize
+}
+
+/// Decodes the base64 lines of an armor body for `Parser::parse_decoded`. The
+/// body is the span of lines the parser has already checked, with their line
+/// breaks, or the body of a message when the parser read it token by token.
+///
+/// Large bodies are split on line boundaries and the parts decoded on several
+/// threads, each computing the CRC-24 of its octets. The octets, checksum and
+/// errors are the same as when decoding on a single thread.
+pub struct BodyDecoder {
+    threads: usize,
+    min_chunk_length: usize
+}
+
+impl BodyDecoder {
+    /// A decoder that uses at most the given number of threads. A single
+    /// thread decodes on the calling thread.
+    pub fn new(threads: usize) -> BodyDecoder {
+        BodyDecoder {
+            threads: cmp::max(threads, 1),
+            min_chunk_length: MIN_CHUNK_LENGTH
+        }
+    }
+
+    /// Decode the lines of an armor body, separated by newlines. A body that is
+    /// not whole base64 groups, or that has data after padding, is a
+    /// `CorruptBody` error.
+    pub fn decode(&self, body: &str) -> ParseResult<DecodedBody> {
+        let bytes = body.as_bytes();
+        let chunks = self.chunks(bytes);
+        if chunks.len() == 1 {
+            let mut octets = Vec::with_capacity(bytes.len() / 4 * 3);
+            let result = decode_lines(bytes, true, &mut octets);
+            return finish(result, octets);
+        }
+
+        let parts: Vec<(ParseResult<Crc24>, Vec<u8>)> = thread::scope(|scope| {
+            let handles: Vec<_> = chunks.iter().enumerate().map(|(i, chunk)| {
+                let last = i + 1 == chunks.len();
+                scope.spawn(move || {
+                    let text = &bytes[chunk.start..chunk.end];
+                    let mut octets = Vec::with_capacity(text.len() / 4 * 3);
+                    let result = decode_lines(text, last, &mut octets).map(|()| {
+                        let mut hasher = Crc24Hasher::new();
+                        hasher.update(&octets);
+                        hasher.finish()
+                    });
+                    (result, octets)
+                })
+            }).collect();
+
+            handles.into_iter().map(|handle| handle.join().unwrap_or_else(|cause| panic::resume_unwind(cause))).collect()
+        });
+
+        let length = parts.iter().map(|(_, octets)| octets.len()).sum();
+        let mut octets = Vec::with_capacity(length);
+        let mut checksum = crc24::crc_octets(&[]);
+        let mut error = None;
+        for (result, mut part) in parts {
+            if error.is_none() {
+                match result {
+                    Ok(part_checksum) => {
+                        checksum = crc24::combine(checksum, part_checksum, part.len());
+                        octets.extend_from_slice(&part);
+                    }
+                    Err(err) => error = Some(err)
+                }
+            }
+            zeroize::zeroize(&mut part);
+        }
+
+        match error {
+            Some(err) => finish(Err(err), octets),
+            None => Ok(DecodedBody::new(octets, checksum))
+        }
+    }
+
+    // Split a body into parts to decode on their own. The body is cut just
+    // after newlines at roughly even offsets, and a part is joined to the one
+    // before it unless it starts on a group boundary and holds base64 data.
+    fn chunks(&self, bytes: &[u8]) -> Vec<Chunk> {
+        let parts = cmp::min(self.threads, bytes.len() / self.min_chunk_length);
+        if parts <= 1 {
+            return vec![Chunk { start: 0, end: bytes.len() }];
+        }
+
+        let mut bounds = vec![0];
+        for i in 1..parts {
+            let target = cmp::max(bytes.len() / parts * i, bounds[bounds.len() - 1]);
+            match bytes[target..].iter().position(|&byte| byte == b'\n') {
+                Some(offset) => bounds.push(target + offset + 1),
+                None => break
+            }
+        }
+        bounds.push(bytes.len());
+        bounds.dedup();
+
+        let newlines: Vec<usize> = thread::scope(|scope| {
+            let handles: Vec<_> = bounds.windows(2).map(|bound| {
+                let text = &bytes[bound[0]..bound[1]];
+                scope.spawn(move || text.iter().filter(|&&byte| byte == b'\n').count())
+            }).collect();
+
+            handles.into_iter().map(|handle| handle.join().unwrap_or_else(|cause| panic::resume_unwind(cause))).collect()
+        });
+
+        let mut chunks: Vec<Chunk> = Vec::with_capacity(newlines.len());
+        let mut characters: usize = 0;
+        for (bound, &lines) in bounds.windows(2).zip(newlines.iter()) {
+            let part_characters = bound[1] - bound[0] - lines;
+            match chunks.last_mut() {
+                Some(chunk) if characters % 4 != 0 || part_characters == 0 => chunk.end = bound[1],
+                _ => chunks.push(Chunk { start: bound[0], end: bound[1] })
+            }
+            characters += part_characters;
+        }
+
+        chunks
+    }
+}
+
+// Wipe the octets decoded before an error, since a body may hold key material.
+fn finish(result: ParseResult<()>, mut octets: Vec<u8>) -> ParseResult<DecodedBody> {
+    match result {
+        Ok(()) => {
+            let checksum = crc24::crc_octets(&octets);
+            Ok(DecodedBody::new(octets, checksum))
+        }
+        Err(err) => {
+            zeroize::zeroize(&mut octets);
+            Err(err)
+        }
+    }
+}
+
+// Decode whole groups of characters, noting whether they end in padding.
+fn decode_groups(groups: &[u8], octets: &mut Vec<u8>, padded: &mut bool) -> ParseResult<()> {
+    if *padded {
+        return Err(ParseError::CorruptBody);
+    }
+    base64::decode_bytes_into(groups, octets).ok_or(ParseError::CorruptBody)?;
+    *padded = groups.last() == Some(&b'=');
+
+    Ok(())
+}
+
+// Decode the lines of a part of a body, which starts on a group boundary.
+// Groups may span lines. Padding may only end the last part.
+fn decode_lines(text: &[u8], last: bool, octets: &mut Vec<u8>) -> ParseResult<()> {
+    let mut group = [0u8; 4];
+    let mut group_length = 0;
+    let mut padded = false;
+    for line in text.split(|&byte| byte == b'\n') {
+        let mut rest = line;
+        if group_length > 0 {
+            let length = cmp::min(4 - group_length, rest.len());
+            group[group_length..group_length + length].copy_from_slice(&rest[..length]);
+            group_length += length;
+            rest = &rest[length..];
+            if group_length < 4 {
+                continue;
+            }
+            group_length = 0;
+            decode_groups(&group, octets, &mut padded)?;
+        }
+
+        let whole = rest.len() / 4 * 4;
+        if whole > 0 {
+            decode_groups(&rest[..whole], octets, &mut padded)?;
+        }
+
+        let tail = &rest[whole..];
+        if !tail.is_empty() {
+            if padded {
+                return Err(ParseError::CorruptBody);
+            }
+            group[..tail.len()].copy_from_slice(tail);
+            group_length = tail.len();
+        }
+    }
+
+    if group_length > 0 || (padded && !last) {
+        return Err(ParseError::CorruptBody);
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use base64;
+    use crc24;
+    use parser::ParseError;
+    use super::BodyDecoder;
+
+
+    // Base64 text of a few kilobytes broken into lines of the given lengths,
+    // cycling through them.
+    fn armor_body(length: usize, line_lengths: &[usize]) -> (Vec<u8>, String) {
+        let octets: Vec<u8> = (0..length).map(|i| (i * 31 + i / 5) as u8).collect();
+        let text = base64::encode(&octets);
+        let mut body = String::new();
+        let mut position = 0;
+        for &line_length in line_lengths.iter().cycle() {
+            if position >= text.len() {
+                break;
+            }
+            let end = ::std::cmp::min(position + line_length, text.len());
+            body.push_str(&text[position..end]);
+            body.push('\n');
+            position = end;
+        }
+
+        (octets, body)
+    }
+
+    fn sequential() -> BodyDecoder {
+        BodyDecoder::new(1)
+    }
+
+    fn parallel() -> BodyDecoder {
+        BodyDecoder { threads: 4, min_chunk_length: 64 }
+    }
+
+    #[test]
+    fn test_parallel_decode() {
+        for line_lengths in [&[64][..], &[76], &[5, 7, 13], &[1]].iter() {
+            for &length in [0, 1, 2, 3, 100, 4000].iter() {
+                let (octets, body) = armor_body(length, line_lengths);
+                let decoded = parallel().decode(&body).unwrap();
+
+                assert_eq!(decoded.octets(), &octets[..]);
+                assert_eq!(decoded.checksum(), crc24::crc_octets(&octets));
+                assert!(format!("{:?}", decoded).contains("[REDACTED; "));
+                assert_eq!(Ok(decoded), sequential().decode(&body));
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_decode_errors() {
+        let (_, body) = armor_body(4000, &[64]);
+        let mut lines: Vec<String> = body.lines().map(String::from).collect();
+        lines[60].replace_range(3..4, "=");
+        let padded_early = lines.join("\n");
+
+        let (_, body) = armor_body(4002, &[5, 7, 13]);
+        let incomplete = format!("{}AB\n", body);
+
+        let (_, padded) = armor_body(4001, &[5, 7, 13]);
+        let tests = vec![
+            (padded_early, Some(ParseError::CorruptBody)),
+            (incomplete, Some(ParseError::CorruptBody)),
+            (padded, None)
+        ];
+        for (body, error) in tests {
+            let expected = sequential().decode(&body);
+            assert_eq!(expected.as_ref().err(), error.as_ref());
+            assert_eq!(parallel().decode(&body), expected);
+        }
+    }
+
+    #[test]
+    fn test_padding_before_more_data() {
+        let body = "Zg==\nZm9v\n";
+        let parallel = BodyDecoder { threads: 4, min_chunk_length: 1 };
+        assert_eq!(parallel.decode(body), Err(ParseError::CorruptBody));
+        assert_eq!(sequential().decode(body), Err(ParseError::CorruptBody));
+        assert_eq!(sequential().decode("Zg==Zm9v"), Err(ParseError::CorruptBody));
+    }
+}
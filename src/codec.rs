@@ -0,0 +1,188 @@
+use std::collections::VecDeque;
+use std::io;
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+use encoder;
+use parser::{ArmorMessage, ParseError};
+use push::{Event, PushParser};
+use zeroize;
+
+
+/// A tokio codec for armor. Decoding yields the events of a push parser as
+/// frames, so a block is de-armored as it arrives on a `FramedRead`. A stream
+/// may hold several blocks one after another, separated by line breaks; each
+/// block ends with its `End` event. Encoding writes whole armor messages.
+pub struct ArmorCodec {
+    parser: PushParser,
+    events: VecDeque<Event>,
+    error: Option<ParseError>,
+    finished: bool
+}
+
+impl ArmorCodec {
+    pub fn new() -> ArmorCodec {
+        ArmorCodec {
+            parser: PushParser::new(),
+            events: VecDeque::new(),
+            error: None,
+            finished: false
+        }
+    }
+
+    // The events parsed before an error are delivered before the error is.
+    fn next_event(&mut self) -> io::Result<Option<Event>> {
+        match self.events.pop_front() {
+            Some(event) => Ok(Some(event)),
+            None => match self.error.take() {
+                Some(err) => Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+                None => Ok(None)
+            }
+        }
+    }
+}
+
+impl Default for ArmorCodec {
+    fn default() -> ArmorCodec {
+        ArmorCodec::new()
+    }
+}
+
+impl Decoder for ArmorCodec {
+    type Item = Event;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Event>> {
+        while self.events.is_empty() && self.error.is_none() && !src.is_empty() {
+            // A new block starts after the line breaks that end the last one.
+            if self.parser.is_done() {
+                let breaks = src.iter().take_while(|&&byte| byte == b'\n' || byte == b'\r').count();
+                src.advance(breaks);
+                if src.is_empty() {
+                    break;
+                }
+                self.parser = PushParser::new();
+            }
+
+            // The parser buffers partial lines itself, but is fed a line at a
+            // time so the input after a tail line is left for the next block.
+            let length = src.iter().position(|&byte| byte == b'\n').map_or(src.len(), |end| end + 1);
+            let mut events = Vec::new();
+            if let Err(err) = self.parser.feed(&src[..length], &mut events) {
+                self.error = Some(err);
+            }
+            self.events.extend(events);
+            zeroize::zeroize(&mut src[..length]);
+            src.advance(length);
+        }
+
+        self.next_event()
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> io::Result<Option<Event>> {
+        if let Some(event) = self.decode(src)? {
+            return Ok(Some(event));
+        }
+        if !self.finished {
+            self.finished = true;
+            let mut events = Vec::new();
+            if let Err(err) = self.parser.finish(&mut events) {
+                self.error = Some(err);
+            }
+            self.events.extend(events);
+        }
+
+        self.next_event()
+    }
+}
+
+impl<'a> Encoder<&'a ArmorMessage> for ArmorCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, message: &'a ArmorMessage, dst: &mut BytesMut) -> io::Result<()> {
+        encoder::write_armor(&mut dst.writer(), message)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+    use lexer::Lexer;
+    use parser::Parser;
+    use push::{Event, PushParser};
+    use super::ArmorCodec;
+
+
+    fn decode_all(text: &str, piece: usize) -> (Vec<Event>, io::Result<()>) {
+        let mut codec = ArmorCodec::new();
+        let mut events = Vec::new();
+        let mut src = BytesMut::new();
+        for chunk in text.as_bytes().chunks(piece) {
+            src.extend_from_slice(chunk);
+            loop {
+                match codec.decode(&mut src) {
+                    Ok(Some(event)) => events.push(event),
+                    Ok(None) => break,
+                    Err(err) => return (events, Err(err))
+                }
+            }
+        }
+        loop {
+            match codec.decode_eof(&mut src) {
+                Ok(Some(event)) => events.push(event),
+                Ok(None) => return (events, Ok(())),
+                Err(err) => return (events, Err(err))
+            }
+        }
+    }
+
+    #[test]
+    fn test_codec_decode() {
+        let text = include_str!("../sample/pub.asc");
+        let mut expected = Vec::new();
+        let mut parser = PushParser::new();
+        parser.feed(text.as_bytes(), &mut expected).unwrap();
+        parser.finish(&mut expected).unwrap();
+
+        for &piece in [1, 100, text.len()].iter() {
+            let (events, result) = decode_all(text, piece);
+            assert!(result.is_ok());
+            assert_eq!(events, expected);
+        }
+
+        let (events, result) = decode_all(&text[..text.len() / 2], 64);
+        assert_eq!(events[0], expected[0]);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_codec_decode_blocks() {
+        let first = include_str!("../sample/pub.asc");
+        let second = include_str!("../sample/message.asc");
+        let mut expected = Vec::new();
+        for text in [first, second].iter() {
+            let mut parser = PushParser::new();
+            parser.feed(text.as_bytes(), &mut expected).unwrap();
+            parser.finish(&mut expected).unwrap();
+        }
+
+        let text = format!("{}\r\n{}", first, second);
+        for &piece in [1, 100, text.len()].iter() {
+            let (events, result) = decode_all(&text, piece);
+            assert!(result.is_ok());
+            assert_eq!(events, expected);
+        }
+    }
+
+    #[test]
+    fn test_codec_encode() {
+        let text = include_str!("../sample/message.asc");
+        let message = Parser::new(Lexer::new(text)).parse().unwrap();
+        let mut dst = BytesMut::new();
+        ArmorCodec::new().encode(&message, &mut dst).unwrap();
+
+        assert_eq!(&dst[..], ::encoder::encode(&message).as_bytes());
+    }
+}
@@ -3,29 +3,145 @@
 // of RFC4880 along with the definition of the CRC octet validator.
 const CRC24_INIT: usize = 0xB704CE;
 const CRC24_POLY: usize = 0x1864CFB;
+const CRC24_MASK: usize = 0xFFFFFF;
+
+// The tables work on the CRC in the top 24 bits of a 32 bit word, so a whole
+// octet can be shifted out of the top of the word at once.
+const TABLE_POLY: u32 = ((CRC24_POLY & CRC24_MASK) << 8) as u32;
+
+// Table k holds the CRC of an octet followed by k zero octets, for
+// processing eight octets per step.
+const TABLES: [[u32; 256]; 8] = crc_tables();
 
 pub type Crc24 = usize;
 
+const fn crc_tables() -> [[u32; 256]; 8] {
+    let mut tables = [[0; 256]; 8];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ TABLE_POLY } else { crc << 1 };
+            bit += 1;
+        }
+        tables[0][i] = crc;
+        i += 1;
+    }
+
+    let mut k = 1;
+    while k < 8 {
+        let mut i = 0;
+        while i < 256 {
+            let previous = tables[k - 1][i];
+            tables[k][i] = (previous << 8) ^ tables[0][(previous >> 24) as usize];
+            i += 1;
+        }
+        k += 1;
+    }
+
+    tables
+}
+
+/// Computes the CRC-24 of a stream of octets fed in pieces.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Crc24Hasher {
+    crc: u32
+}
+
+impl Crc24Hasher {
+    pub fn new() -> Crc24Hasher {
+        Crc24Hasher {
+            crc: (CRC24_INIT << 8) as u32
+        }
+    }
+
+    pub fn update(&mut self, octets: &[u8]) {
+        let mut crc = self.crc;
+        let mut chunks = octets.chunks_exact(8);
+        for chunk in &mut chunks {
+            let high = crc ^ u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            let low = u32::from_be_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+            crc = TABLES[7][(high >> 24) as usize]
+                ^ TABLES[6][((high >> 16) & 0xFF) as usize]
+                ^ TABLES[5][((high >> 8) & 0xFF) as usize]
+                ^ TABLES[4][(high & 0xFF) as usize]
+                ^ TABLES[3][(low >> 24) as usize]
+                ^ TABLES[2][((low >> 16) & 0xFF) as usize]
+                ^ TABLES[1][((low >> 8) & 0xFF) as usize]
+                ^ TABLES[0][(low & 0xFF) as usize];
+        }
+        for &octet in chunks.remainder() {
+            crc = (crc << 8) ^ TABLES[0][((crc >> 24) ^ octet as u32) as usize];
+        }
+
+        self.crc = crc;
+    }
+
+    /// The CRC-24 of the octets fed so far.
+    pub fn finish(&self) -> Crc24 {
+        (self.crc >> 8) as Crc24
+    }
+}
+
+impl Default for Crc24Hasher {
+    fn default() -> Crc24Hasher {
+        Crc24Hasher::new()
+    }
+}
+
 // This is an adaption of the CRC-24 algorithm from section 6.1 of RFC4880.
 pub fn crc_octets(octets: &[u8]) -> Crc24 {
-    let mut crc: Crc24 = CRC24_INIT;
-    for octet in octets {
-        crc ^= (*octet as usize) << 16;
-        for _ in 0..8 {
-            crc <<= 1;
-            if crc & 0x1000000 != 0 {
-                crc ^= CRC24_POLY;
-            }
+    let mut hasher = Crc24Hasher::new();
+    hasher.update(octets);
+    hasher.finish()
+}
+
+// The product of two polynomials modulo the CRC-24 polynomial.
+fn multiply_mod(a: Crc24, b: Crc24) -> Crc24 {
+    let mut product = 0;
+    for bit in (0..24).rev() {
+        product <<= 1;
+        if product & 0x1000000 != 0 {
+            product ^= CRC24_POLY;
+        }
+        if b & (1 << bit) != 0 {
+            product ^= a;
         }
     }
-    // Fit crc24 into 24 bits.
-    crc & 0xFFFFFF
+
+    product
+}
+
+// Running the CRC over a zero octet multiplies its register by x^8 modulo
+// the polynomial, so running it over n zero octets multiplies it by x^(8n).
+fn zero_octets_factor(mut length: usize) -> Crc24 {
+    let mut factor = 1;
+    let mut square = 0x100;
+    while length > 0 {
+        if length & 1 != 0 {
+            factor = multiply_mod(factor, square);
+        }
+        square = multiply_mod(square, square);
+        length >>= 1;
+    }
+
+    factor
 }
 
+/// The CRC-24 of the concatenation of two octet sequences, from the CRC-24 of
+/// each sequence and the length of the second one. This lets the checksums of
+/// chunks that were decoded separately be merged without reading them again.
+pub fn combine(crc_a: Crc24, crc_b: Crc24, length_b: usize) -> Crc24 {
+    // Both CRCs start from CRC24_INIT, so the CRC of the second sequence
+    // already carries the initial value run over its length.
+    let factor = zero_octets_factor(length_b);
+    (multiply_mod((crc_a ^ CRC24_INIT) & CRC24_MASK, factor) ^ crc_b) & CRC24_MASK
+}
 
 #[cfg(test)]
 mod tests {
-    use super::Crc24;
+    use super::{Crc24, Crc24Hasher};
 
 
     struct TestCase {
@@ -90,4 +206,55 @@ mod tests {
     fn test_crc_octets_empty_case() {
         run_tests(&crc_octets_empty_case());
     }
+
+    // The bitwise algorithm from section 6.1 of RFC4880.
+    fn crc_octets_bitwise(octets: &[u8]) -> Crc24 {
+        let mut crc = super::CRC24_INIT;
+        for octet in octets {
+            crc ^= (*octet as usize) << 16;
+            for _ in 0..8 {
+                crc <<= 1;
+                if crc & 0x1000000 != 0 {
+                    crc ^= super::CRC24_POLY;
+                }
+            }
+        }
+
+        crc & 0xFFFFFF
+    }
+
+    fn octets(length: usize) -> Vec<u8> {
+        (0..length).map(|i| (i * 7 + i / 5) as u8).collect()
+    }
+
+    #[test]
+    fn test_crc_table_matches_bitwise() {
+        for length in 0..40 {
+            let octets = octets(length);
+            assert_eq!(super::crc_octets(&octets), crc_octets_bitwise(&octets));
+        }
+        let octets = octets(100_003);
+        assert_eq!(super::crc_octets(&octets), crc_octets_bitwise(&octets));
+    }
+
+    #[test]
+    fn test_crc24_hasher() {
+        let octets = octets(1000);
+        for &split in [0, 1, 7, 8, 9, 500, 999, 1000].iter() {
+            let mut hasher = Crc24Hasher::new();
+            hasher.update(&octets[..split]);
+            hasher.update(&octets[split..]);
+            assert_eq!(hasher.finish(), super::crc_octets(&octets));
+        }
+    }
+
+    #[test]
+    fn test_combine() {
+        let octets = octets(2049);
+        for &split in [0, 1, 3, 8, 1024, 2048, 2049].iter() {
+            let (a, b) = octets.split_at(split);
+            let combined = super::combine(super::crc_octets(a), super::crc_octets(b), b.len());
+            assert_eq!(combined, super::crc_octets(&octets));
+        }
+    }
 }
@@ -0,0 +1,304 @@
+// Self contained implementations of SHA-1 and SHA-256 from FIPS 180-4, used for
+// computing key fingerprints.
+
+const SHA1_INIT: [u32; 5] = [0x6745_2301, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476, 0xC3D2_E1F0];
+
+const SHA256_INIT: [u32; 8] = [
+    0x6A09_E667, 0xBB67_AE85, 0x3C6E_F372, 0xA54F_F53A,
+    0x510E_527F, 0x9B05_688C, 0x1F83_D9AB, 0x5BE0_CD19
+];
+
+const SHA256_K: [u32; 64] = [
+    0x428A_2F98, 0x7137_4491, 0xB5C0_FBCF, 0xE9B5_DBA5, 0x3956_C25B, 0x59F1_11F1, 0x923F_82A4, 0xAB1C_5ED5,
+    0xD807_AA98, 0x1283_5B01, 0x2431_85BE, 0x550C_7DC3, 0x72BE_5D74, 0x80DE_B1FE, 0x9BDC_06A7, 0xC19B_F174,
+    0xE49B_69C1, 0xEFBE_4786, 0x0FC1_9DC6, 0x240C_A1CC, 0x2DE9_2C6F, 0x4A74_84AA, 0x5CB0_A9DC, 0x76F9_88DA,
+    0x983E_5152, 0xA831_C66D, 0xB003_27C8, 0xBF59_7FC7, 0xC6E0_0BF3, 0xD5A7_9147, 0x06CA_6351, 0x1429_2967,
+    0x27B7_0A85, 0x2E1B_2138, 0x4D2C_6DFC, 0x5338_0D13, 0x650A_7354, 0x766A_0ABB, 0x81C2_C92E, 0x9272_2C85,
+    0xA2BF_E8A1, 0xA81A_664B, 0xC24B_8B70, 0xC76C_51A3, 0xD192_E819, 0xD699_0624, 0xF40E_3585, 0x106A_A070,
+    0x19A4_C116, 0x1E37_6C08, 0x2748_774C, 0x34B0_BCB5, 0x391C_0CB3, 0x4ED8_AA4A, 0x5B9C_CA4F, 0x682E_6FF3,
+    0x748F_82EE, 0x78A5_636F, 0x84C8_7814, 0x8CC7_0208, 0x90BE_FFFA, 0xA450_6CEB, 0xBEF9_A3F7, 0xC671_78F2
+];
+
+const BLOCK_LENGTH: usize = 64;
+
+
+// Both hashes share the Merkle-Damgard construction with 64 octet blocks and a
+// big endian bit length in the final block.
+struct BlockBuffer {
+    block: [u8; BLOCK_LENGTH],
+    filled: usize,
+    length: u64
+}
+
+impl BlockBuffer {
+    fn new() -> BlockBuffer {
+        BlockBuffer {
+            block: [0; BLOCK_LENGTH],
+            filled: 0,
+            length: 0
+        }
+    }
+
+    fn update<F>(&mut self, mut data: &[u8], mut compress: F)
+        where F: FnMut(&[u8; BLOCK_LENGTH])
+    {
+        self.length = self.length.wrapping_add(data.len() as u64);
+        while !data.is_empty() {
+            let amount = (BLOCK_LENGTH - self.filled).min(data.len());
+            self.block[self.filled..self.filled + amount].copy_from_slice(&data[..amount]);
+            self.filled += amount;
+            data = &data[amount..];
+
+            if self.filled == BLOCK_LENGTH {
+                compress(&self.block);
+                self.filled = 0;
+            }
+        }
+    }
+
+    fn finish<F>(&mut self, mut compress: F)
+        where F: FnMut(&[u8; BLOCK_LENGTH])
+    {
+        let bit_length = self.length.wrapping_mul(8);
+        self.block[self.filled] = 0x80;
+        for octet in self.block[self.filled + 1..].iter_mut() {
+            *octet = 0;
+        }
+        if self.filled + 1 > BLOCK_LENGTH - 8 {
+            compress(&self.block);
+            self.block = [0; BLOCK_LENGTH];
+        }
+        self.block[BLOCK_LENGTH - 8..].copy_from_slice(&bit_length.to_be_bytes());
+        compress(&self.block);
+    }
+}
+
+fn read_words(block: &[u8; BLOCK_LENGTH], words: &mut [u32]) {
+    for (word, chunk) in words.iter_mut().zip(block.chunks(4)) {
+        *word = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+}
+
+pub struct Sha1 {
+    state: [u32; 5],
+    buffer: BlockBuffer
+}
+
+impl Sha1 {
+    pub fn new() -> Sha1 {
+        Sha1 {
+            state: SHA1_INIT,
+            buffer: BlockBuffer::new()
+        }
+    }
+
+    fn compress(state: &mut [u32; 5], block: &[u8; BLOCK_LENGTH]) {
+        let mut w = [0u32; 80];
+        read_words(block, &mut w[..16]);
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = *state;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6)
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (value, add) in state.iter_mut().zip([a, b, c, d, e].iter()) {
+            *value = value.wrapping_add(*add);
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.buffer.update(data, |block| Sha1::compress(state, block));
+    }
+
+    pub fn finish(mut self) -> [u8; 20] {
+        let state = &mut self.state;
+        self.buffer.finish(|block| Sha1::compress(state, block));
+
+        let mut digest = [0; 20];
+        for (chunk, word) in digest.chunks_mut(4).zip(self.state.iter()) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+
+        digest
+    }
+}
+
+impl Default for Sha1 {
+    fn default() -> Sha1 {
+        Sha1::new()
+    }
+}
+
+pub struct Sha256 {
+    state: [u32; 8],
+    buffer: BlockBuffer
+}
+
+impl Sha256 {
+    pub fn new() -> Sha256 {
+        Sha256 {
+            state: SHA256_INIT,
+            buffer: BlockBuffer::new()
+        }
+    }
+
+    fn compress(state: &mut [u32; 8], block: &[u8; BLOCK_LENGTH]) {
+        let mut w = [0u32; 64];
+        read_words(block, &mut w[..16]);
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+        for (&k, &word) in SHA256_K.iter().zip(w.iter()) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let temp1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(k).wrapping_add(word);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+
+        for (value, add) in state.iter_mut().zip([a, b, c, d, e, f, g, h].iter()) {
+            *value = value.wrapping_add(*add);
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.buffer.update(data, |block| Sha256::compress(state, block));
+    }
+
+    pub fn finish(mut self) -> [u8; 32] {
+        let state = &mut self.state;
+        self.buffer.finish(|block| Sha256::compress(state, block));
+
+        let mut digest = [0; 32];
+        for (chunk, word) in digest.chunks_mut(4).zip(self.state.iter()) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+
+        digest
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Sha256 {
+        Sha256::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{Sha1, Sha256};
+
+
+    fn sha1(data: &[u8]) -> [u8; 20] {
+        let mut hasher = Sha1::new();
+        hasher.update(data);
+        hasher.finish()
+    }
+
+    fn sha256(data: &[u8]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(data);
+        hasher.finish()
+    }
+
+    fn to_hex(octets: &[u8]) -> String {
+        octets.iter().map(|octet| format!("{:02x}", octet)).collect()
+    }
+
+    struct TestCase {
+        data: Vec<u8>,
+        sha1: &'static str,
+        sha256: &'static str
+    }
+
+    struct Test {
+        data: Vec<TestCase>
+    }
+
+    // Test vectors from FIPS 180-4 examples and NIST CAVS.
+    fn hash_test_cases() -> Test {
+        Test {
+            data: vec![
+                TestCase {
+                    data: vec![],
+                    sha1: "da39a3ee5e6b4b0d3255bfef95601890afd80709",
+                    sha256: "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+                },
+                TestCase {
+                    data: b"abc".to_vec(),
+                    sha1: "a9993e364706816aba3e25717850c26c9cd0d89d",
+                    sha256: "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+                },
+                TestCase {
+                    data: b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq".to_vec(),
+                    sha1: "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+                    sha256: "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+                },
+                TestCase {
+                    data: vec![b'a'; 1_000_000],
+                    sha1: "34aa973cd4c4daa4f61eeb2bdbad27316534016f",
+                    sha256: "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+                }
+            ]
+        }
+    }
+
+    fn run_tests(test: &Test) {
+        for test_case in test.data.iter() {
+            assert_eq!(to_hex(&sha1(&test_case.data)), test_case.sha1);
+            assert_eq!(to_hex(&sha256(&test_case.data)), test_case.sha256);
+        }
+    }
+
+    #[test]
+    fn test_hashes() {
+        run_tests(&hash_test_cases());
+    }
+
+    #[test]
+    fn test_incremental_update() {
+        let data: Vec<u8> = (0..1000).map(|i| (i * 7) as u8).collect();
+        for split in [0, 1, 55, 56, 63, 64, 65, 999].iter() {
+            let mut hasher = Sha1::new();
+            hasher.update(&data[..*split]);
+            hasher.update(&data[*split..]);
+            assert_eq!(hasher.finish(), sha1(&data));
+
+            let mut hasher = Sha256::new();
+            hasher.update(&data[..*split]);
+            hasher.update(&data[*split..]);
+            assert_eq!(hasher.finish(), sha256(&data));
+        }
+    }
+}
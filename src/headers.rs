@@ -0,0 +1,246 @@
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+use parser::HeaderType;
+
+
+// Unicode line and paragraph separators are not control characters, but some
+// readers break lines on them.
+const LINE_TERMINATORS: [char; 4] = ['\n', '\r', '\u{2028}', '\u{2029}'];
+
+
+/// A header key or value that would change the structure of the armor it is
+/// written into.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ValidationError {
+    EmptyKey,
+    /// A key character that is not visible ASCII, or is a colon.
+    InvalidKeyCharacter { offset: usize, character: char },
+    LineTerminator { offset: usize, character: char },
+    ControlCharacter { offset: usize, character: char }
+}
+
+pub type ValidationResult<T> = Result<T, ValidationError>;
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationError::EmptyKey => write!(f, "Empty header key."),
+            ValidationError::InvalidKeyCharacter { offset, character } => {
+                write!(f, "Invalid character U+{:04X} in header key at offset {}.", character as u32, offset)
+            }
+            ValidationError::LineTerminator { offset, character } => {
+                write!(f, "Line terminator U+{:04X} in header value at offset {}.", character as u32, offset)
+            }
+            ValidationError::ControlCharacter { offset, character } => {
+                write!(f, "Control character U+{:04X} in header value at offset {}.", character as u32, offset)
+            }
+        }
+    }
+}
+
+impl error::Error for ValidationError {
+    fn description(&self) -> &str {
+        match *self {
+            ValidationError::EmptyKey => "A header key is empty.",
+            ValidationError::InvalidKeyCharacter { .. } => "A header key may only contain visible ASCII other than ':'.",
+            ValidationError::LineTerminator { .. } => "A header value contains a line terminator.",
+            ValidationError::ControlCharacter { .. } => "A header value contains a control character."
+        }
+    }
+}
+
+fn is_key_character(ch: char) -> bool {
+    ch.is_ascii_graphic() && ch != ':'
+}
+
+fn is_value_character(ch: char) -> bool {
+    !ch.is_control() && !LINE_TERMINATORS.contains(&ch)
+}
+
+/// Check that a header key can be written on an armor header line. Keys are
+/// visible ASCII without colons, so they cannot end early or hide a value.
+pub fn validate_key(key: &str) -> ValidationResult<()> {
+    if key.is_empty() {
+        return Err(ValidationError::EmptyKey);
+    }
+
+    match key.char_indices().find(|&(_, ch)| !is_key_character(ch)) {
+        Some((offset, character)) => Err(ValidationError::InvalidKeyCharacter { offset, character }),
+        None => Ok(())
+    }
+}
+
+/// Check that a header value stays on its armor header line. A line
+/// terminator in a value could end the header block early or forge a tail
+/// line.
+pub fn validate_value(value: &str) -> ValidationResult<()> {
+    match value.char_indices().find(|&(_, ch)| !is_value_character(ch)) {
+        Some((offset, character)) if LINE_TERMINATORS.contains(&character) => {
+            Err(ValidationError::LineTerminator { offset, character })
+        }
+        Some((offset, character)) => Err(ValidationError::ControlCharacter { offset, character }),
+        None => Ok(())
+    }
+}
+
+/// Check every armor header in a header block.
+pub fn validate_headers(headers: &[(HeaderType, String)]) -> ValidationResult<()> {
+    for (header_type, value) in headers {
+        validate_key(header_type.name())?;
+        validate_value(value)?;
+    }
+
+    Ok(())
+}
+
+// A backslash is escaped too, so text that already looks like an escape can
+// be told apart from one.
+fn escape<F>(text: &str, allowed: F) -> Cow<'_, str> where F: Fn(char) -> bool {
+    let allowed = |ch| ch != '\\' && allowed(ch);
+    if text.chars().all(allowed) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut escaped = String::with_capacity(text.len() + 8);
+    for ch in text.chars() {
+        if allowed(ch) {
+            escaped.push(ch);
+        } else {
+            escaped.extend(ch.escape_unicode());
+        }
+    }
+
+    Cow::Owned(escaped)
+}
+
+/// Replace the characters a header key may not contain with `\u{..}` escapes.
+/// An empty key is written as `\u{}`, so the line still has a key.
+pub fn escape_key(key: &str) -> Cow<'_, str> {
+    if key.is_empty() {
+        return Cow::Borrowed("\\u{}");
+    }
+
+    escape(key, is_key_character)
+}
+
+/// Replace the characters a header value may not contain with `\u{..}`
+/// escapes.
+pub fn escape_value(value: &str) -> Cow<'_, str> {
+    escape(value, is_value_character)
+}
+
+/// An armor header block whose keys and values have been validated, so it can
+/// be encoded without changing the structure of the armor.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ArmorHeaders {
+    headers: Vec<(HeaderType, String)>
+}
+
+impl ArmorHeaders {
+    pub fn new() -> ArmorHeaders {
+        ArmorHeaders {
+            headers: Vec::new()
+        }
+    }
+
+    /// Validate a header block.
+    pub fn from_vec(headers: Vec<(HeaderType, String)>) -> ValidationResult<ArmorHeaders> {
+        validate_headers(&headers)?;
+
+        Ok(ArmorHeaders { headers })
+    }
+
+    /// Append a header, unless its key or value is rejected.
+    pub fn push<S: Into<String>>(&mut self, header_type: HeaderType, value: S) -> ValidationResult<()> {
+        let value = value.into();
+        validate_key(header_type.name())?;
+        validate_value(&value)?;
+        self.headers.push((header_type, value));
+
+        Ok(())
+    }
+
+    pub fn as_slice(&self) -> &[(HeaderType, String)] {
+        &self.headers
+    }
+
+    pub fn len(&self) -> usize {
+        self.headers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
+    pub fn into_vec(self) -> Vec<(HeaderType, String)> {
+        self.headers
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use parser::HeaderType;
+    use super::{ArmorHeaders, ValidationError};
+
+
+    #[test]
+    fn test_armor_headers() {
+        let mut headers = ArmorHeaders::new();
+        headers.push(HeaderType::Version, "OpenPrivacy 0.99").unwrap();
+        headers.push(HeaderType::OtherHeader(String::from("X-Build")), "r1\u{2013}rc").unwrap();
+
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers.as_slice()[1].1, "r1\u{2013}rc");
+    }
+
+    #[test]
+    fn test_rejected_headers() {
+        let headers = vec![
+            (HeaderType::Comment, "hi\n-----END PGP MESSAGE-----", ValidationError::LineTerminator {
+                offset: 2, character: '\n'
+            }),
+            (HeaderType::Comment, "a\rb", ValidationError::LineTerminator { offset: 1, character: '\r' }),
+            (HeaderType::Comment, "a\u{2028}", ValidationError::LineTerminator { offset: 1, character: '\u{2028}' }),
+            (HeaderType::Comment, "bell\u{7}", ValidationError::ControlCharacter { offset: 4, character: '\u{7}' }),
+            (HeaderType::OtherHeader(String::new()), "", ValidationError::EmptyKey),
+            (HeaderType::OtherHeader(String::from("Key:")), "", ValidationError::InvalidKeyCharacter {
+                offset: 3, character: ':'
+            }),
+            (HeaderType::OtherHeader(String::from("Two words")), "", ValidationError::InvalidKeyCharacter {
+                offset: 3, character: ' '
+            }),
+            (HeaderType::OtherHeader(String::from("Cl\u{E9}")), "", ValidationError::InvalidKeyCharacter {
+                offset: 2, character: '\u{E9}'
+            })
+        ];
+
+        for (header_type, value, error) in headers {
+            let mut armor_headers = ArmorHeaders::new();
+            assert_eq!(armor_headers.push(header_type.clone(), value), Err(error));
+            assert!(armor_headers.is_empty());
+            assert_eq!(ArmorHeaders::from_vec(vec![(header_type, String::from(value))]), Err(error));
+        }
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(super::escape_value("plain"), "plain");
+        assert_eq!(super::escape_value("a\n-----END PGP MESSAGE-----"), "a\\u{a}-----END PGP MESSAGE-----");
+        assert_eq!(super::escape_key("Key: value"), "Key\\u{3a}\\u{20}value");
+        assert_eq!(super::escape_value("a\\u{a}b\n"), "a\\u{5c}u{a}b\\u{a}");
+        assert_eq!(super::escape_key("A\\B"), "A\\u{5c}B");
+        assert_eq!(super::escape_key(""), "\\u{}");
+    }
+
+    #[test]
+    fn test_validate_headers() {
+        let headers = vec![
+            (HeaderType::Comment, String::from("ok")),
+            (HeaderType::OtherHeader(String::new()), String::from("v"))
+        ];
+        assert_eq!(super::validate_headers(&headers), Err(ValidationError::EmptyKey));
+        assert_eq!(super::validate_headers(&headers[..1]), Ok(()));
+    }
+}
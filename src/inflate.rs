@@ -0,0 +1,418 @@
+// A decoder for the DEFLATE format of RFC1951 and the ZLIB wrapper of RFC1950,
+// which OpenPGP uses for the ZIP and ZLIB compression algorithms.
+
+const MAX_BITS: usize = 15;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31,
+    35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2,
+    3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193,
+    257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6,
+    7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13
+];
+
+// The order in which the code length code lengths are sent.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum InflateError {
+    Corrupt,
+    // The output would grow past the limit the caller set.
+    OutputLimit
+}
+
+pub type InflateResult<T> = Result<T, InflateError>;
+
+
+// Reads bits least significant bit first, as section 3.1.1 of RFC1951 packs them.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u32,
+    count: u32
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader {
+            data,
+            position: 0,
+            buffer: 0,
+            count: 0
+        }
+    }
+
+    fn bits(&mut self, count: u32) -> Option<u32> {
+        while self.count < count {
+            let octet = *self.data.get(self.position)?;
+            self.position += 1;
+            self.buffer |= (octet as u32) << self.count;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1 << count) - 1);
+        self.buffer >>= count;
+        self.count -= count;
+
+        Some(value)
+    }
+
+    // Discard the bits left in the current octet.
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+// A canonical Huffman code stored as the number of codes of each length and
+// the symbols ordered by code.
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>
+}
+
+impl Huffman {
+    // Over-subscribed code lengths are rejected. Incomplete codes are allowed
+    // since a distance code with a single code is incomplete.
+    fn new(lengths: &[u8]) -> Option<Huffman> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+
+        let mut left: i32 = 1;
+        for &count in counts[1..].iter() {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return None;
+            }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 1];
+        for length in 1..MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        Some(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Option<u16> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for &count in self.counts[1..].iter() {
+            code |= reader.bits(1)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return self.symbols.get((index + code - first) as usize).cloned();
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        None
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    for (symbol, length) in lengths.iter_mut().enumerate() {
+        *length = match symbol {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8
+        };
+    }
+
+    (Huffman::new(&lengths).unwrap(), Huffman::new(&[5; 30]).unwrap())
+}
+
+// Section 3.2.7 of RFC1951.
+fn dynamic_codes(reader: &mut BitReader) -> Option<(Huffman, Huffman)> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return None;
+    }
+
+    let mut code_lengths = [0u8; 19];
+    for &index in CODE_LENGTH_ORDER[..code_length_count].iter() {
+        code_lengths[index] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0u8; literal_count + distance_count];
+    let mut index = 0;
+    while index < lengths.len() {
+        let symbol = code_length_code.decode(reader)?;
+        let (length, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => (*lengths[..index].last()?, 3 + reader.bits(2)? as usize),
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize)
+        };
+        if index + repeat > lengths.len() {
+            return None;
+        }
+        for length_slot in lengths[index..index + repeat].iter_mut() {
+            *length_slot = length;
+        }
+        index += repeat;
+    }
+
+    // A block without an end of block code cannot be decoded.
+    if lengths[256] == 0 {
+        return None;
+    }
+
+    Some((Huffman::new(&lengths[..literal_count])?, Huffman::new(&lengths[literal_count..])?))
+}
+
+// Check that `length` more octets fit in the output before they are written,
+// so a small stream of long back references cannot grow it without bound.
+fn reserve(output: &[u8], length: usize, limit: usize) -> InflateResult<()> {
+    if length > limit.saturating_sub(output.len()) {
+        return Err(InflateError::OutputLimit);
+    }
+
+    Ok(())
+}
+
+// The length and distance of a back reference, from section 3.2.5 of RFC1951.
+fn back_reference(reader: &mut BitReader, symbol: usize, distance_code: &Huffman) -> Option<(usize, usize)> {
+    let index = symbol - 257;
+    let length = *LENGTH_BASE.get(index)? as usize + reader.bits(*LENGTH_EXTRA.get(index)? as u32)? as usize;
+    let index = distance_code.decode(reader)? as usize;
+    let distance = *DISTANCE_BASE.get(index)? as usize + reader.bits(*DISTANCE_EXTRA.get(index)? as u32)? as usize;
+
+    Some((length, distance))
+}
+
+fn inflate_block(reader: &mut BitReader, literal_code: &Huffman, distance_code: &Huffman,
+                 output: &mut Vec<u8>, limit: usize) -> InflateResult<()>
+{
+    loop {
+        let symbol = literal_code.decode(reader).ok_or(InflateError::Corrupt)? as usize;
+        match symbol {
+            0..=255 => {
+                reserve(output, 1, limit)?;
+                output.push(symbol as u8);
+            }
+            256 => return Ok(()),
+            _ => {
+                let (length, distance) = back_reference(reader, symbol, distance_code).ok_or(InflateError::Corrupt)?;
+                if distance > output.len() {
+                    return Err(InflateError::Corrupt);
+                }
+                reserve(output, length, limit)?;
+                let start = output.len() - distance;
+                for i in 0..length {
+                    let octet = output[start + i];
+                    output.push(octet);
+                }
+            }
+        }
+    }
+}
+
+fn stored_block(reader: &mut BitReader, output: &mut Vec<u8>, limit: usize) -> InflateResult<()> {
+    reader.align();
+    let header = reader.data.get(reader.position..reader.position + 4).ok_or(InflateError::Corrupt)?;
+    let length = u16::from_le_bytes([header[0], header[1]]);
+    let complement = u16::from_le_bytes([header[2], header[3]]);
+    if length != !complement {
+        return Err(InflateError::Corrupt);
+    }
+    reader.position += 4;
+    let block = reader.data.get(reader.position..reader.position + length as usize).ok_or(InflateError::Corrupt)?;
+    reserve(output, block.len(), limit)?;
+    output.extend_from_slice(block);
+    reader.position += length as usize;
+
+    Ok(())
+}
+
+// Returns the decompressed data and the number of input octets used.
+fn inflate_stream(data: &[u8], limit: usize) -> InflateResult<(Vec<u8>, usize)> {
+    let mut reader = BitReader::new(data);
+    let mut output = Vec::new();
+    loop {
+        let last = reader.bits(1).ok_or(InflateError::Corrupt)? == 1;
+        match reader.bits(2).ok_or(InflateError::Corrupt)? {
+            0 => stored_block(&mut reader, &mut output, limit)?,
+            1 => {
+                let (literal_code, distance_code) = fixed_codes();
+                inflate_block(&mut reader, &literal_code, &distance_code, &mut output, limit)?
+            }
+            2 => {
+                let (literal_code, distance_code) = dynamic_codes(&mut reader).ok_or(InflateError::Corrupt)?;
+                inflate_block(&mut reader, &literal_code, &distance_code, &mut output, limit)?
+            }
+            _ => return Err(InflateError::Corrupt)
+        }
+
+        if last {
+            return Ok((output, reader.position));
+        }
+    }
+}
+
+/// Decompress raw DEFLATE data into at most `limit` octets.
+pub fn inflate(data: &[u8], limit: usize) -> InflateResult<Vec<u8>> {
+    inflate_stream(data, limit).map(|(output, _)| output)
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(4096) {
+        for &octet in chunk {
+            a += octet as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+
+    (b << 16) | a
+}
+
+/// Decompress DEFLATE data in a ZLIB wrapper into at most `limit` octets and
+/// verify its Adler-32 checksum.
+pub fn zlib_decompress(data: &[u8], limit: usize) -> InflateResult<Vec<u8>> {
+    let (&method, rest) = data.split_first().ok_or(InflateError::Corrupt)?;
+    let (&flags, rest) = rest.split_first().ok_or(InflateError::Corrupt)?;
+    let has_dictionary = flags & 0x20 != 0;
+    if method & 0x0F != 8 || ((method as u16) << 8 | flags as u16) % 31 != 0 || has_dictionary {
+        return Err(InflateError::Corrupt);
+    }
+
+    let (output, used) = inflate_stream(rest, limit)?;
+    let checksum = rest.get(used..used + 4).ok_or(InflateError::Corrupt)?;
+    if u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]) != adler32(&output) {
+        return Err(InflateError::Corrupt);
+    }
+
+    Ok(output)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::InflateError;
+
+
+    const LIMIT: usize = 1 << 20;
+
+    struct TestCase {
+        compressed: Vec<u8>,
+        data: Vec<u8>
+    }
+
+    struct Test {
+        data: Vec<TestCase>
+    }
+
+    // Raw DEFLATE streams with stored, fixed Huffman and dynamic Huffman blocks.
+    fn inflate_test_cases() -> Test {
+        Test {
+            data: vec![
+                TestCase {
+                    compressed: vec![0x01, 0x03, 0x00, 0xFC, 0xFF, b'a', b'b', b'c'],
+                    data: b"abc".to_vec()
+                },
+                TestCase {
+                    compressed: vec![0x03, 0x00],
+                    data: vec![]
+                },
+                TestCase {
+                    compressed: vec![
+                        0x4B, 0x4C, 0x4A, 0x4E, 0x49, 0x4D, 0x4B, 0xCF, 0xC8, 0xCC, 0xCA, 0xCE, 0xC9, 0xCD, 0xCB,
+                        0x2F, 0x28, 0x2C, 0x2A, 0x2E, 0x29, 0x2D, 0x2B, 0xAF, 0xA8, 0xAC, 0x4A, 0x24, 0x43, 0x06, 0x00
+                    ],
+                    data: b"abcdefghijklmnopqrstuvwxyz".repeat(3)
+                },
+                TestCase {
+                    compressed: vec![
+                        0xCD, 0xCB, 0xD1, 0x01, 0x80, 0x10, 0x14, 0x46, 0xE1, 0xF7, 0xA6, 0xF8, 0x27, 0x30, 0x4B, 0x0F,
+                        0x16, 0x50, 0x11, 0x15, 0x37, 0x84, 0x98, 0xBE, 0x3B, 0x46, 0xCF, 0xE7, 0x3B, 0xD2, 0x6A, 0xC4,
+                        0xE2, 0xD6, 0x13, 0x4B, 0xA2, 0x16, 0x60, 0xE8, 0xC5, 0x51, 0xFC, 0x9D, 0x41, 0x55, 0x27, 0x3C,
+                        0x9C, 0x2F, 0x35, 0x3A, 0x36, 0xDA, 0x05, 0xE4, 0x3F, 0xF0, 0xAC, 0xD8, 0xF9, 0x8E, 0x85, 0x51,
+                        0x73, 0x8F, 0x85, 0x71, 0x55, 0x73, 0x1A, 0x3A, 0xE0, 0x72, 0xB1, 0x50, 0xE2, 0x77, 0xCF, 0x62,
+                        0xFA, 0x00
+                    ],
+                    data: [&b"The quick brown fox jumps over the lazy dog. ".repeat(4)[..],
+                           &b"Pack my box with five dozen liquor jugs.\n"[..]].concat()
+                }
+            ]
+        }
+    }
+
+    fn run_tests(test: &Test) {
+        for test_case in test.data.iter() {
+            assert_eq!(super::inflate(&test_case.compressed, LIMIT), Ok(test_case.data.clone()));
+        }
+    }
+
+    #[test]
+    fn test_inflate() {
+        run_tests(&inflate_test_cases());
+    }
+
+    #[test]
+    fn test_inflate_errors() {
+        // A reserved block type, a stored block with a bad length complement,
+        // a back reference before the start of the output and a missing end.
+        assert_eq!(super::inflate(&[0x07], LIMIT), Err(InflateError::Corrupt));
+        assert_eq!(super::inflate(&[0x01, 0x03, 0x00, 0xFC, 0xFE, b'a', b'b', b'c'], LIMIT), Err(InflateError::Corrupt));
+        assert_eq!(super::inflate(&[0x03, 0x02], LIMIT), Err(InflateError::Corrupt));
+        assert_eq!(super::inflate(&[0x4B, 0x4C], LIMIT), Err(InflateError::Corrupt));
+    }
+
+    #[test]
+    fn test_output_limit() {
+        // 78 octets that inflate to 64 KiB of zeros.
+        let compressed = [
+            &[0xED, 0xC1, 0x01, 0x01, 0x00, 0x00, 0x00, 0x80, 0x90, 0xFE, 0xAF, 0xEE, 0x08, 0x0A][..],
+            &[0x00; 63][..],
+            &[0x6A][..]
+        ].concat();
+
+        assert_eq!(super::inflate(&compressed, 1 << 16).map(|output| output.len()), Ok(1 << 16));
+        assert_eq!(super::inflate(&compressed, (1 << 16) - 1), Err(InflateError::OutputLimit));
+        assert_eq!(super::inflate(&compressed, 1000), Err(InflateError::OutputLimit));
+
+        let stored = [0x01, 0x03, 0x00, 0xFC, 0xFF, b'a', b'b', b'c'];
+        assert_eq!(super::inflate(&stored, 2), Err(InflateError::OutputLimit));
+    }
+
+    #[test]
+    fn test_zlib_decompress() {
+        let mut compressed = vec![
+            0x78, 0x9C, 0xCB, 0x48, 0xCD, 0xC9, 0xC9, 0xD7, 0x51, 0xC8, 0x40, 0xA2, 0xB8, 0x00, 0x4B, 0x07, 0x06, 0xDF
+        ];
+        assert_eq!(super::zlib_decompress(&compressed, LIMIT), Ok(b"hello, hello, hello\n".to_vec()));
+        assert_eq!(super::zlib_decompress(&compressed, 10), Err(InflateError::OutputLimit));
+
+        compressed[17] ^= 0x01;
+        assert_eq!(super::zlib_decompress(&compressed, LIMIT), Err(InflateError::Corrupt));
+    }
+}
@@ -0,0 +1,613 @@
+use std::error;
+use std::fmt;
+use hash::{Sha1, Sha256};
+use packet::{self, Packet, PacketTag, PacketError};
+use parser::ArmorMessage;
+
+
+/// The public key algorithms from section 9.1 of RFC9580.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PublicKeyAlgorithm {
+    Rsa,
+    RsaEncryptOnly,
+    RsaSignOnly,
+    Elgamal,
+    Dsa,
+    Ecdh,
+    Ecdsa,
+    ElgamalEncryptOrSign,
+    EdDsaLegacy,
+    X25519,
+    X448,
+    Ed25519,
+    Ed448,
+    Other(u8)
+}
+
+impl PublicKeyAlgorithm {
+    pub fn from_u8(algorithm: u8) -> PublicKeyAlgorithm {
+        match algorithm {
+            1  => PublicKeyAlgorithm::Rsa,
+            2  => PublicKeyAlgorithm::RsaEncryptOnly,
+            3  => PublicKeyAlgorithm::RsaSignOnly,
+            16 => PublicKeyAlgorithm::Elgamal,
+            17 => PublicKeyAlgorithm::Dsa,
+            18 => PublicKeyAlgorithm::Ecdh,
+            19 => PublicKeyAlgorithm::Ecdsa,
+            20 => PublicKeyAlgorithm::ElgamalEncryptOrSign,
+            22 => PublicKeyAlgorithm::EdDsaLegacy,
+            25 => PublicKeyAlgorithm::X25519,
+            26 => PublicKeyAlgorithm::X448,
+            27 => PublicKeyAlgorithm::Ed25519,
+            28 => PublicKeyAlgorithm::Ed448,
+            other => PublicKeyAlgorithm::Other(other)
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            PublicKeyAlgorithm::Rsa => 1,
+            PublicKeyAlgorithm::RsaEncryptOnly => 2,
+            PublicKeyAlgorithm::RsaSignOnly => 3,
+            PublicKeyAlgorithm::Elgamal => 16,
+            PublicKeyAlgorithm::Dsa => 17,
+            PublicKeyAlgorithm::Ecdh => 18,
+            PublicKeyAlgorithm::Ecdsa => 19,
+            PublicKeyAlgorithm::ElgamalEncryptOrSign => 20,
+            PublicKeyAlgorithm::EdDsaLegacy => 22,
+            PublicKeyAlgorithm::X25519 => 25,
+            PublicKeyAlgorithm::X448 => 26,
+            PublicKeyAlgorithm::Ed25519 => 27,
+            PublicKeyAlgorithm::Ed448 => 28,
+            PublicKeyAlgorithm::Other(algorithm) => algorithm
+        }
+    }
+}
+
+impl fmt::Display for PublicKeyAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PublicKeyAlgorithm::Rsa => write!(f, "RSA"),
+            PublicKeyAlgorithm::RsaEncryptOnly => write!(f, "RSA (encrypt only)"),
+            PublicKeyAlgorithm::RsaSignOnly => write!(f, "RSA (sign only)"),
+            PublicKeyAlgorithm::Elgamal => write!(f, "Elgamal"),
+            PublicKeyAlgorithm::Dsa => write!(f, "DSA"),
+            PublicKeyAlgorithm::Ecdh => write!(f, "ECDH"),
+            PublicKeyAlgorithm::Ecdsa => write!(f, "ECDSA"),
+            PublicKeyAlgorithm::ElgamalEncryptOrSign => write!(f, "Elgamal (encrypt or sign)"),
+            PublicKeyAlgorithm::EdDsaLegacy => write!(f, "EdDSA"),
+            PublicKeyAlgorithm::X25519 => write!(f, "X25519"),
+            PublicKeyAlgorithm::X448 => write!(f, "X448"),
+            PublicKeyAlgorithm::Ed25519 => write!(f, "Ed25519"),
+            PublicKeyAlgorithm::Ed448 => write!(f, "Ed448"),
+            PublicKeyAlgorithm::Other(algorithm) => write!(f, "algorithm {}", algorithm)
+        }
+    }
+}
+
+fn write_hex(f: &mut fmt::Formatter, octets: &[u8]) -> fmt::Result {
+    for octet in octets {
+        write!(f, "{:02X}", octet)?;
+    }
+
+    Ok(())
+}
+
+/// The eight octet identifier of a key.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct KeyId([u8; 8]);
+
+impl KeyId {
+    pub fn new(octets: [u8; 8]) -> KeyId {
+        KeyId(octets)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for KeyId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_hex(f, &self.0)
+    }
+}
+
+/// A key fingerprint. Version 4 keys use a SHA-1 fingerprint and version 6
+/// keys use a SHA-256 fingerprint, following section 5.5.4 of RFC9580.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Fingerprint {
+    V4([u8; 20]),
+    V6([u8; 32])
+}
+
+impl Fingerprint {
+    pub fn as_bytes(&self) -> &[u8] {
+        match *self {
+            Fingerprint::V4(ref octets) => octets,
+            Fingerprint::V6(ref octets) => octets
+        }
+    }
+
+    /// The key ID is the low 64 bits of a version 4 fingerprint and the high
+    /// 64 bits of a version 6 fingerprint.
+    pub fn key_id(&self) -> KeyId {
+        let octets = self.as_bytes();
+        let start = match *self {
+            Fingerprint::V4(_) => octets.len() - 8,
+            Fingerprint::V6(_) => 0
+        };
+        let mut key_id = [0; 8];
+        key_id.copy_from_slice(&octets[start..start + 8]);
+
+        KeyId(key_id)
+    }
+}
+
+// Upper case hexadecimal in groups of four digits, e.g. `9A7F 5461 46F9 ...`.
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, group) in self.as_bytes().chunks(2).enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write_hex(f, group)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum KeyError {
+    CorruptBody,
+    MalformedPacket(PacketError),
+    NotAKey(PacketTag),
+    UnsupportedVersion(u8),
+    UnsupportedAlgorithm(u8),
+    TruncatedKey
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            KeyError::CorruptBody => write!(f, "Corrupt Base64 data."),
+            KeyError::MalformedPacket(e) => write!(f, "Malformed packet: {}", e),
+            KeyError::NotAKey(tag) => write!(f, "A {:?} packet is not a key packet.", tag),
+            KeyError::UnsupportedVersion(version) => write!(f, "Unsupported key version {}.", version),
+            KeyError::UnsupportedAlgorithm(algorithm) => write!(f, "Unsupported public key algorithm {}.", algorithm),
+            KeyError::TruncatedKey => write!(f, "Truncated key material.")
+        }
+    }
+}
+
+impl error::Error for KeyError {
+    fn description(&self) -> &str {
+        match *self {
+            KeyError::CorruptBody => "The Base 64 payload of the armor message was corrupted.",
+            KeyError::MalformedPacket(_) => "The packet stream in the armor body is malformed.",
+            KeyError::NotAKey(_) => "The packet is not a public or secret key packet.",
+            KeyError::UnsupportedVersion(_) => "Only version 4 and version 6 keys have a supported fingerprint.",
+            KeyError::UnsupportedAlgorithm(_) => "The length of the public key material of the algorithm is not known.",
+            KeyError::TruncatedKey => "The key packet ends in the middle of the public key material."
+        }
+    }
+}
+
+pub type KeyResult<T> = Result<T, KeyError>;
+
+// A cursor over the public key material of a version 4 key packet.
+struct Material<'a> {
+    data: &'a [u8],
+    position: usize
+}
+
+impl<'a> Material<'a> {
+    fn skip(&mut self, length: usize) -> KeyResult<()> {
+        if length > self.data.len() - self.position {
+            return Err(KeyError::TruncatedKey);
+        }
+        self.position += length;
+
+        Ok(())
+    }
+
+    fn read_octet(&mut self) -> KeyResult<u8> {
+        let octet = *self.data.get(self.position).ok_or(KeyError::TruncatedKey)?;
+        self.position += 1;
+
+        Ok(octet)
+    }
+
+    // Section 3.2 of RFC9580: a two octet bit count followed by the integer.
+    fn skip_mpis(&mut self, count: usize) -> KeyResult<()> {
+        for _ in 0..count {
+            let bits = ((self.read_octet()? as usize) << 8) | self.read_octet()? as usize;
+            self.skip((bits + 7) / 8)?;
+        }
+
+        Ok(())
+    }
+
+    // Curve OIDs and KDF parameters are prefixed by a one octet length.
+    fn skip_prefixed(&mut self) -> KeyResult<()> {
+        let length = self.read_octet()? as usize;
+        self.skip(length)
+    }
+}
+
+// The length of the algorithm specific public key material at the start of
+// `data`, from section 5.5.5 of RFC9580. Secret key packets carry the secret
+// material right after it, so the length has to be worked out field by field.
+fn material_length(algorithm: PublicKeyAlgorithm, data: &[u8]) -> KeyResult<usize> {
+    let mut material = Material { data, position: 0 };
+    match algorithm {
+        PublicKeyAlgorithm::Rsa | PublicKeyAlgorithm::RsaEncryptOnly | PublicKeyAlgorithm::RsaSignOnly => {
+            material.skip_mpis(2)?
+        }
+        PublicKeyAlgorithm::Elgamal | PublicKeyAlgorithm::ElgamalEncryptOrSign => material.skip_mpis(3)?,
+        PublicKeyAlgorithm::Dsa => material.skip_mpis(4)?,
+        PublicKeyAlgorithm::Ecdh => {
+            material.skip_prefixed()?;
+            material.skip_mpis(1)?;
+            material.skip_prefixed()?;
+        }
+        PublicKeyAlgorithm::Ecdsa | PublicKeyAlgorithm::EdDsaLegacy => {
+            material.skip_prefixed()?;
+            material.skip_mpis(1)?;
+        }
+        PublicKeyAlgorithm::X25519 | PublicKeyAlgorithm::Ed25519 => material.skip(32)?,
+        PublicKeyAlgorithm::X448 => material.skip(56)?,
+        PublicKeyAlgorithm::Ed448 => material.skip(57)?,
+        PublicKeyAlgorithm::Other(algorithm) => return Err(KeyError::UnsupportedAlgorithm(algorithm))
+    }
+
+    Ok(material.position)
+}
+
+/// How the secret key material of a secret key packet is protected, read from
+/// the S2K usage octet of section 5.5.3 of RFC9580.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum KeyProtection {
+    /// The secret key material is stored in the clear.
+    Unprotected,
+    /// The secret key material is encrypted with a passphrase. The value is
+    /// the S2K usage octet, which tells how the key is derived.
+    Passphrase(u8)
+}
+
+impl KeyProtection {
+    pub fn from_s2k_usage(usage: u8) -> KeyProtection {
+        match usage {
+            0 => KeyProtection::Unprotected,
+            other => KeyProtection::Passphrase(other)
+        }
+    }
+
+    pub fn is_protected(self) -> bool {
+        self != KeyProtection::Unprotected
+    }
+}
+
+/// The identifying data of a primary key or subkey packet.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct KeyInfo {
+    tag: PacketTag,
+    version: u8,
+    algorithm: PublicKeyAlgorithm,
+    created: u32,
+    fingerprint: Fingerprint,
+    protection: Option<KeyProtection>,
+    offset: usize
+}
+
+impl KeyInfo {
+    pub fn tag(&self) -> PacketTag {
+        self.tag
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn algorithm(&self) -> PublicKeyAlgorithm {
+        self.algorithm
+    }
+
+    /// The creation time in seconds since the Unix epoch.
+    pub fn created(&self) -> u32 {
+        self.created
+    }
+
+    pub fn fingerprint(&self) -> &Fingerprint {
+        &self.fingerprint
+    }
+
+    pub fn key_id(&self) -> KeyId {
+        self.fingerprint.key_id()
+    }
+
+    /// How the secret key material is protected. Public key packets carry
+    /// no secret key material and return `None`.
+    pub fn protection(&self) -> Option<KeyProtection> {
+        self.protection
+    }
+
+    /// The offset of the key packet in the packet stream.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn is_subkey(&self) -> bool {
+        self.tag == PacketTag::PublicSubkey || self.tag == PacketTag::SecretSubkey
+    }
+
+    pub fn is_secret(&self) -> bool {
+        is_secret_tag(self.tag)
+    }
+}
+
+fn is_secret_tag(tag: PacketTag) -> bool {
+    tag == PacketTag::SecretKey || tag == PacketTag::SecretSubkey
+}
+
+/// Compute the fingerprint and read the identifying fields of a key packet.
+/// Secret key packets get the fingerprint of their public part.
+pub fn key_info(packet: &Packet) -> KeyResult<KeyInfo> {
+    match packet.tag() {
+        PacketTag::PublicKey | PacketTag::PublicSubkey | PacketTag::SecretKey | PacketTag::SecretSubkey => {}
+        tag => return Err(KeyError::NotAKey(tag))
+    }
+
+    let body = packet.body();
+    if body.len() < 6 {
+        return Err(KeyError::TruncatedKey);
+    }
+    let version = body[0];
+    let created = u32::from_be_bytes([body[1], body[2], body[3], body[4]]);
+    let algorithm = PublicKeyAlgorithm::from_u8(body[5]);
+
+    let (fingerprint, public_length) = match version {
+        4 => {
+            // The fingerprint of a public key packet covers the whole body, so
+            // it does not need the algorithm to be known.
+            let material = match material_length(algorithm, &body[6..]) {
+                Err(KeyError::UnsupportedAlgorithm(_)) if !is_secret_tag(packet.tag()) => body.len() - 6,
+                result => result?
+            };
+            let length = 6 + material;
+            if length > 0xFFFF {
+                return Err(KeyError::TruncatedKey);
+            }
+            let mut hasher = Sha1::new();
+            hasher.update(&[0x99]);
+            hasher.update(&(length as u16).to_be_bytes());
+            hasher.update(&body[..length]);
+            (Fingerprint::V4(hasher.finish()), length)
+        }
+        6 => {
+            // Version 6 keys state the length of their public key material.
+            if body.len() < 10 {
+                return Err(KeyError::TruncatedKey);
+            }
+            let material = u32::from_be_bytes([body[6], body[7], body[8], body[9]]) as usize;
+            if material > body.len() - 10 {
+                return Err(KeyError::TruncatedKey);
+            }
+            let length = 10 + material;
+            let mut hasher = Sha256::new();
+            hasher.update(&[0x9B]);
+            hasher.update(&(length as u32).to_be_bytes());
+            hasher.update(&body[..length]);
+            (Fingerprint::V6(hasher.finish()), length)
+        }
+        other => return Err(KeyError::UnsupportedVersion(other))
+    };
+
+    // The S2K usage octet follows the public part of a secret key packet.
+    let protection = match packet.tag() {
+        PacketTag::SecretKey | PacketTag::SecretSubkey => {
+            let usage = *body.get(public_length).ok_or(KeyError::TruncatedKey)?;
+            Some(KeyProtection::from_s2k_usage(usage))
+        }
+        _ => None
+    };
+
+    Ok(KeyInfo {
+        tag: packet.tag(),
+        version,
+        algorithm,
+        created,
+        fingerprint,
+        protection,
+        offset: packet.offset()
+    })
+}
+
+/// The primary keys and subkeys in a packet stream, in stream order. Subkeys
+/// that cannot be read, such as secret subkeys of an unknown algorithm, are
+/// left out rather than failing the listing.
+pub fn keys(data: &[u8]) -> KeyResult<Vec<KeyInfo>> {
+    let mut keys = Vec::new();
+    for packet in packet::packets(data) {
+        let packet = packet.map_err(KeyError::MalformedPacket)?;
+        match packet.tag() {
+            PacketTag::PublicKey | PacketTag::SecretKey => keys.push(key_info(&packet)?),
+            PacketTag::PublicSubkey | PacketTag::SecretSubkey => {
+                if let Ok(key) = key_info(&packet) {
+                    keys.push(key);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(keys)
+}
+
+/// The primary keys and subkeys in the body of an armor message.
+pub fn message_keys(message: &ArmorMessage) -> KeyResult<Vec<KeyInfo>> {
+    let data = message.decode_secret_body().ok_or(KeyError::CorruptBody)?;
+    keys(&data)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use lexer::Lexer;
+    use parser::Parser;
+    use packet::PacketTag;
+    use super::{PublicKeyAlgorithm, KeyError, KeyProtection};
+
+
+    struct TestCase {
+        text: &'static str,
+        keys: Vec<(PacketTag, PublicKeyAlgorithm, &'static str, &'static str)>
+    }
+
+    struct Test {
+        data: Vec<TestCase>
+    }
+
+    fn sample_test_cases() -> Test {
+        Test {
+            data: vec![
+                TestCase {
+                    text: include_str!("../sample/pub.asc"),
+                    keys: vec![
+                        (PacketTag::PublicKey, PublicKeyAlgorithm::Dsa,
+                         "9A7F 5461 46F9 EE0F CBDB CB45 DE28 B655 F2C6 584A", "DE28B655F2C6584A"),
+                        (PacketTag::PublicSubkey, PublicKeyAlgorithm::Elgamal,
+                         "E0CE 6BF0 CE26 0A3D 8F1C 27DB 95A6 15C2 8EE6 3F01", "95A615C28EE63F01")
+                    ]
+                },
+                TestCase {
+                    text: include_str!("../sample/priv.asc"),
+                    keys: vec![
+                        (PacketTag::SecretKey, PublicKeyAlgorithm::Dsa,
+                         "9A7F 5461 46F9 EE0F CBDB CB45 DE28 B655 F2C6 584A", "DE28B655F2C6584A"),
+                        (PacketTag::SecretSubkey, PublicKeyAlgorithm::Elgamal,
+                         "E0CE 6BF0 CE26 0A3D 8F1C 27DB 95A6 15C2 8EE6 3F01", "95A615C28EE63F01")
+                    ]
+                }
+            ]
+        }
+    }
+
+    fn run_tests(test: &Test) {
+        for test_case in test.data.iter() {
+            let message = Parser::new(Lexer::new(test_case.text)).parse().unwrap();
+            let keys = super::message_keys(&message).unwrap();
+            let result: Vec<_> = keys.iter()
+                                     .map(|key| (key.tag(), key.algorithm(),
+                                                 key.fingerprint().to_string(), key.key_id().to_string()))
+                                     .collect();
+            let expected: Vec<_> = test_case.keys
+                                            .iter()
+                                            .map(|&(tag, algorithm, fingerprint, key_id)| {
+                                                (tag, algorithm, String::from(fingerprint), String::from(key_id))
+                                            })
+                                            .collect();
+
+            assert_eq!(result, expected);
+            assert!(keys.iter().all(|key| key.version() == 4));
+        }
+    }
+
+    #[test]
+    fn test_sample_fingerprints() {
+        run_tests(&sample_test_cases());
+    }
+
+    // The version 6 Ed25519 primary key from appendix A.3 of RFC9580.
+    #[test]
+    fn test_v6_fingerprint() {
+        let mut data = vec![0xC6, 0x2A, 0x06, 0x63, 0x87, 0x7F, 0xE3, 0x1B, 0x00, 0x00, 0x00, 0x20];
+        data.extend_from_slice(&[
+            0xF9, 0x4D, 0xA7, 0xBB, 0x48, 0xD6, 0x0A, 0x61, 0xE5, 0x67, 0x70, 0x6A, 0x65, 0x87, 0xD0, 0x33,
+            0x19, 0x99, 0xBB, 0x9D, 0x89, 0x1A, 0x08, 0x24, 0x2E, 0xAD, 0x84, 0x54, 0x3D, 0xF8, 0x95, 0xA3
+        ]);
+
+        let keys = super::keys(&data).unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].version(), 6);
+        assert_eq!(keys[0].algorithm(), PublicKeyAlgorithm::Ed25519);
+        assert_eq!(keys[0].created(), 0x6387_7FE3);
+        assert_eq!(keys[0].fingerprint().to_string(),
+                   "CB18 6C4F 0609 A697 E4D5 2DFA 6C72 2B0C 1F1E 27C1 8A56 708F 6525 EC27 BAD9 ACC9");
+        assert_eq!(keys[0].key_id().to_string(), "CB186C4F0609A697");
+    }
+
+    #[test]
+    fn test_key_protection() {
+        let texts = [include_str!("../sample/pub.asc"), include_str!("../sample/priv.asc")];
+        let protections: Vec<Vec<_>> = texts.iter().map(|text| {
+            let message = Parser::new(Lexer::new(text)).parse().unwrap();
+            super::message_keys(&message).unwrap().iter().map(|key| key.protection()).collect()
+        }).collect();
+        assert_eq!(protections, vec![
+            vec![None, None],
+            vec![Some(KeyProtection::Unprotected), Some(KeyProtection::Unprotected)]
+        ]);
+
+        // The RFC9580 version 6 public key turned into a secret key with S2K usage 254.
+        let mut data = vec![0xC5, 0x2B, 0x06, 0x63, 0x87, 0x7F, 0xE3, 0x1B, 0x00, 0x00, 0x00, 0x20];
+        data.extend_from_slice(&[
+            0xF9, 0x4D, 0xA7, 0xBB, 0x48, 0xD6, 0x0A, 0x61, 0xE5, 0x67, 0x70, 0x6A, 0x65, 0x87, 0xD0, 0x33,
+            0x19, 0x99, 0xBB, 0x9D, 0x89, 0x1A, 0x08, 0x24, 0x2E, 0xAD, 0x84, 0x54, 0x3D, 0xF8, 0x95, 0xA3,
+            0xFE
+        ]);
+        let keys = super::keys(&data).unwrap();
+        assert!(keys[0].is_secret());
+        assert_eq!(keys[0].protection(), Some(KeyProtection::Passphrase(254)));
+        assert!(keys[0].protection().unwrap().is_protected());
+
+        data[1] = 0x2A;
+        data.pop();
+        assert_eq!(super::keys(&data), Err(KeyError::TruncatedKey));
+    }
+
+    #[test]
+    fn test_key_errors() {
+        let errors = vec![
+            (vec![0xC6, 0x03, 0x04, 0x00, 0x00], KeyError::TruncatedKey),
+            (vec![0xC6, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01], KeyError::UnsupportedVersion(3)),
+            (vec![0xC5, 0x07, 0x04, 0x00, 0x00, 0x00, 0x00, 0x63, 0x00], KeyError::UnsupportedAlgorithm(99)),
+            (vec![0xC6, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x10], KeyError::TruncatedKey),
+            (vec![0xC6, 0x05, 0x04], KeyError::MalformedPacket(::packet::PacketError::TruncatedBody))
+        ];
+
+        for (data, error) in errors {
+            assert_eq!(super::keys(&data), Err(error));
+        }
+    }
+
+    #[test]
+    fn test_unknown_algorithm_fingerprint() {
+        let body = [0x04, 0x00, 0x00, 0x00, 0x01, 0x63, 0x01, 0x02, 0x03];
+        let data = [&[0xC6, body.len() as u8][..], &body[..]].concat();
+        let mut hasher = ::hash::Sha1::new();
+        hasher.update(&[0x99, 0x00, body.len() as u8]);
+        hasher.update(&body);
+
+        let keys = super::keys(&data).unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].algorithm(), PublicKeyAlgorithm::Other(99));
+        assert_eq!(keys[0].fingerprint().as_bytes(), &hasher.finish()[..]);
+    }
+
+    #[test]
+    fn test_bad_subkey() {
+        let text = include_str!("../sample/priv.asc");
+        let message = Parser::new(Lexer::new(text)).parse().unwrap();
+        let mut data = message.decode_body().unwrap();
+        // A version 3 subkey, then a secret subkey of an unknown algorithm.
+        data.extend_from_slice(&[0xCE, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        data.extend_from_slice(&[0xC7, 0x07, 0x04, 0x00, 0x00, 0x00, 0x00, 0x63, 0x00]);
+
+        let keys = super::keys(&data).unwrap();
+        assert_eq!(keys.len(), 2);
+        assert!(!keys[0].is_subkey());
+        assert!(keys[1].is_subkey());
+    }
+}
@@ -0,0 +1,294 @@
+use std::error;
+use std::fmt;
+use packet::{self, PacketTag, PacketError};
+use parser::{ArmorMessage, MessageType};
+
+
+// The packets an OpenPGP message can start with, following the message grammar
+// in section 11.3 of RFC4880.
+const MESSAGE_PACKETS: [PacketTag; 10] = [
+    PacketTag::PublicKeyEncryptedSessionKey,
+    PacketTag::SymmetricKeyEncryptedSessionKey,
+    PacketTag::Signature,
+    PacketTag::OnePassSignature,
+    PacketTag::CompressedData,
+    PacketTag::SymmetricallyEncryptedData,
+    PacketTag::LiteralData,
+    PacketTag::SymEncryptedIntegrityProtectedData,
+    PacketTag::AeadEncryptedData,
+    PacketTag::Marker
+];
+
+const PUBLIC_KEY_PACKETS: [PacketTag; 1] = [PacketTag::PublicKey];
+const SECRET_KEY_PACKETS: [PacketTag; 1] = [PacketTag::SecretKey];
+const SIGNATURE_PACKETS: [PacketTag; 1] = [PacketTag::Signature];
+
+
+/// A mismatch between the armor label and the packets in the armor body.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LabelError {
+    CorruptBody,
+    EmptyBody,
+    MalformedPacket(PacketError),
+    UnexpectedPacket { offset: usize, tag: PacketTag },
+    SecretKeyMaterial { offset: usize, tag: PacketTag }
+}
+
+impl fmt::Display for LabelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LabelError::CorruptBody => write!(f, "Corrupt Base64 data."),
+            LabelError::EmptyBody => write!(f, "The armor body contains no packets."),
+            LabelError::MalformedPacket(e) => write!(f, "Malformed packet: {}", e),
+            LabelError::UnexpectedPacket { offset, tag } => {
+                write!(f, "Unexpected {:?} packet at offset {}.", tag, offset)
+            }
+            LabelError::SecretKeyMaterial { offset, tag } => {
+                write!(f, "Secret key material in a {:?} packet at offset {}.", tag, offset)
+            }
+        }
+    }
+}
+
+impl error::Error for LabelError {
+    fn description(&self) -> &str {
+        match *self {
+            LabelError::CorruptBody => "The Base 64 payload of the armor message was corrupted.",
+            LabelError::EmptyBody => "The armor body does not contain any packets.",
+            LabelError::MalformedPacket(_) => "The packet stream in the armor body is malformed.",
+            LabelError::UnexpectedPacket { .. } => "The armor body contains a packet not allowed by its label.",
+            LabelError::SecretKeyMaterial { .. } => "A public key block contains secret key packets."
+        }
+    }
+}
+
+/// The packet tags allowed at the start of the body of an armor message with
+/// the given type. Parts after the first part of a multipart message carry
+/// the continuation of a packet, so any content is allowed there.
+pub fn allowed_first_packets(message_type: &MessageType) -> Option<&'static [PacketTag]> {
+    match *message_type {
+        MessageType::PGPMessage => Some(&MESSAGE_PACKETS),
+        MessageType::PGPPublicKeyBlock => Some(&PUBLIC_KEY_PACKETS),
+        MessageType::PGPPrivateKeyBlock => Some(&SECRET_KEY_PACKETS),
+        MessageType::PGPSignature => Some(&SIGNATURE_PACKETS),
+        MessageType::PGPMessagePartXofY(1, _) | MessageType::PGPMessagePartX(1) => Some(&MESSAGE_PACKETS),
+        MessageType::PGPMessagePartXofY(..) | MessageType::PGPMessagePartX(_) => None
+    }
+}
+
+fn is_secret_key(tag: PacketTag) -> bool {
+    tag == PacketTag::SecretKey || tag == PacketTag::SecretSubkey
+}
+
+// Marker and padding packets carry nothing and may appear anywhere.
+fn is_ignored(tag: PacketTag) -> bool {
+    tag == PacketTag::Marker || tag == PacketTag::Padding
+}
+
+/// Check that a decoded armor body is consistent with the armor label. Secret
+/// key packets anywhere in a public key block are reported ahead of any other
+/// mismatch, and a signature block may only contain signature packets.
+pub fn check_packets(message_type: &MessageType, data: &[u8]) -> Result<(), LabelError> {
+    let allowed = match allowed_first_packets(message_type) {
+        Some(allowed) => allowed,
+        None => return Ok(())
+    };
+
+    match *message_type {
+        MessageType::PGPMessagePartXofY(..) | MessageType::PGPMessagePartX(_) => {
+            return check_first_part(allowed, data);
+        }
+        _ => {}
+    }
+
+    let mut packets = Vec::new();
+    for packet in packet::packets(data) {
+        packets.push(packet.map_err(LabelError::MalformedPacket)?);
+    }
+
+    if *message_type == MessageType::PGPPublicKeyBlock {
+        if let Some(packet) = packets.iter().find(|packet| is_secret_key(packet.tag())) {
+            return Err(LabelError::SecretKeyMaterial { offset: packet.offset(), tag: packet.tag() });
+        }
+    }
+
+    let mut significant = packets.iter().filter(|packet| !is_ignored(packet.tag()));
+    match significant.next() {
+        Some(packet) if !allowed.contains(&packet.tag()) => {
+            return Err(LabelError::UnexpectedPacket { offset: packet.offset(), tag: packet.tag() });
+        }
+        Some(_) => {}
+        None => return Err(LabelError::EmptyBody)
+    }
+
+    if *message_type == MessageType::PGPSignature {
+        if let Some(packet) = significant.find(|packet| packet.tag() != PacketTag::Signature) {
+            return Err(LabelError::UnexpectedPacket { offset: packet.offset(), tag: packet.tag() });
+        }
+    }
+
+    Ok(())
+}
+
+// The first part of a split message ends wherever the split fell, usually in
+// the middle of a packet, so only the header of its first packet is checked.
+fn check_first_part(allowed: &[PacketTag], data: &[u8]) -> Result<(), LabelError> {
+    let mut offset = 0;
+    for packet in packet::packets(data) {
+        let (start, tag) = match packet {
+            Ok(ref packet) if is_ignored(packet.tag()) => {
+                offset = packet.offset() + packet.length();
+                continue;
+            }
+            Ok(packet) => (packet.offset(), packet.tag()),
+            Err(PacketError::TruncatedHeader) | Err(PacketError::TruncatedBody) => {
+                (offset, packet::header_tag(data[offset]).map_err(LabelError::MalformedPacket)?)
+            }
+            Err(err) => return Err(LabelError::MalformedPacket(err))
+        };
+        if !allowed.contains(&tag) {
+            return Err(LabelError::UnexpectedPacket { offset: start, tag });
+        }
+        return Ok(());
+    }
+
+    Err(LabelError::EmptyBody)
+}
+
+/// Check that the body of an armor message is consistent with its label.
+pub fn check_label(message: &ArmorMessage) -> Result<(), LabelError> {
+    let data = message.decode_secret_body().ok_or(LabelError::CorruptBody)?;
+    check_packets(message.header_type(), &data)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use lexer::Lexer;
+    use parser::{Parser, MessageType};
+    use packet::{PacketTag, PacketError};
+    use super::LabelError;
+
+
+    struct TestCase {
+        message_type: MessageType,
+        data: Vec<u8>,
+        result: Result<(), LabelError>
+    }
+
+    struct Test {
+        data: Vec<TestCase>
+    }
+
+    fn label_test_cases() -> Test {
+        // New format packets with one octet bodies.
+        let public_key = [0xC6, 0x01, 0x04];
+        let secret_key = [0xC5, 0x01, 0x04];
+        let secret_subkey = [0xC7, 0x01, 0x04];
+        let user_id = [0xCD, 0x01, b'a'];
+        let signature = [0xC2, 0x01, 0x04];
+        let literal = [0xCB, 0x01, b'b'];
+        let marker = [0xCA, 0x03, b'P', b'G', b'P'];
+
+        Test {
+            data: vec![
+                TestCase {
+                    message_type: MessageType::PGPPublicKeyBlock,
+                    data: [&public_key[..], &user_id[..], &signature[..]].concat(),
+                    result: Ok(())
+                },
+                TestCase {
+                    message_type: MessageType::PGPPublicKeyBlock,
+                    data: [&public_key[..], &user_id[..], &secret_subkey[..]].concat(),
+                    result: Err(LabelError::SecretKeyMaterial { offset: 6, tag: PacketTag::SecretSubkey })
+                },
+                TestCase {
+                    message_type: MessageType::PGPPublicKeyBlock,
+                    data: [&secret_key[..], &user_id[..]].concat(),
+                    result: Err(LabelError::SecretKeyMaterial { offset: 0, tag: PacketTag::SecretKey })
+                },
+                TestCase {
+                    message_type: MessageType::PGPPrivateKeyBlock,
+                    data: [&secret_key[..], &user_id[..], &secret_subkey[..]].concat(),
+                    result: Ok(())
+                },
+                TestCase {
+                    message_type: MessageType::PGPPrivateKeyBlock,
+                    data: [&public_key[..], &user_id[..]].concat(),
+                    result: Err(LabelError::UnexpectedPacket { offset: 0, tag: PacketTag::PublicKey })
+                },
+                TestCase {
+                    message_type: MessageType::PGPSignature,
+                    data: [&signature[..], &signature[..]].concat(),
+                    result: Ok(())
+                },
+                TestCase {
+                    message_type: MessageType::PGPSignature,
+                    data: [&signature[..], &literal[..]].concat(),
+                    result: Err(LabelError::UnexpectedPacket { offset: 3, tag: PacketTag::LiteralData })
+                },
+                TestCase {
+                    message_type: MessageType::PGPMessage,
+                    data: [&marker[..], &literal[..]].concat(),
+                    result: Ok(())
+                },
+                TestCase {
+                    message_type: MessageType::PGPMessage,
+                    data: public_key.to_vec(),
+                    result: Err(LabelError::UnexpectedPacket { offset: 0, tag: PacketTag::PublicKey })
+                },
+                TestCase {
+                    message_type: MessageType::PGPMessagePartXofY(1, 2),
+                    data: user_id.to_vec(),
+                    result: Err(LabelError::UnexpectedPacket { offset: 0, tag: PacketTag::UserID })
+                },
+                TestCase {
+                    message_type: MessageType::PGPMessagePartXofY(1, 2),
+                    data: vec![0xCB, 0x05, b'b'],
+                    result: Ok(())
+                },
+                TestCase {
+                    message_type: MessageType::PGPMessagePartX(1),
+                    data: [&marker[..], &[0xCD, 0x05, b'a'][..]].concat(),
+                    result: Err(LabelError::UnexpectedPacket { offset: 5, tag: PacketTag::UserID })
+                },
+                TestCase {
+                    message_type: MessageType::PGPMessagePartXofY(2, 2),
+                    data: vec![0x00, 0x01],
+                    result: Ok(())
+                },
+                TestCase {
+                    message_type: MessageType::PGPMessage,
+                    data: marker.to_vec(),
+                    result: Err(LabelError::EmptyBody)
+                },
+                TestCase {
+                    message_type: MessageType::PGPMessage,
+                    data: vec![0xCB, 0x05, b'b'],
+                    result: Err(LabelError::MalformedPacket(PacketError::TruncatedBody))
+                }
+            ]
+        }
+    }
+
+    fn run_tests(test: &Test) {
+        for test_case in test.data.iter() {
+            assert_eq!(super::check_packets(&test_case.message_type, &test_case.data), test_case.result);
+        }
+    }
+
+    #[test]
+    fn test_check_packets() {
+        run_tests(&label_test_cases());
+    }
+
+    #[test]
+    fn test_check_label_samples() {
+        for text in [include_str!("../sample/message.asc"),
+                     include_str!("../sample/pub.asc"),
+                     include_str!("../sample/priv.asc")].iter() {
+            let message = Parser::new(Lexer::new(text)).parse().unwrap();
+            assert_eq!(super::check_label(&message), Ok(()));
+        }
+    }
+}
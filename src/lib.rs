@@ -1,7 +1,44 @@
+#[cfg(feature = "async")]
+extern crate futures_io;
+#[cfg(feature = "codec")]
+extern crate bytes;
+#[cfg(feature = "codec")]
+extern crate tokio_util;
+#[cfg(test)]
+extern crate futures;
+
 pub mod ascii_armor;
 
 mod token;
 mod base64;
+#[cfg(feature = "simd")]
+mod base64_simd;
 mod crc24;
 mod lexer;
 mod parser;
+mod scanner;
+mod mime;
+mod encoder;
+mod pgp_mime;
+mod mailbox;
+mod packet;
+mod label;
+mod hash;
+mod key;
+mod user;
+mod signature;
+mod inflate;
+mod literal;
+mod zeroize;
+mod secrets;
+mod redact;
+mod headers;
+mod body;
+mod push;
+mod visitor;
+mod validate;
+mod limits;
+#[cfg(feature = "async")]
+mod async_armor;
+#[cfg(feature = "codec")]
+mod codec;
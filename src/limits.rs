@@ -0,0 +1,156 @@
+use std::fmt;
+use parser::{ParseError, ParseResult};
+
+
+const MAX_HEADERS: usize = 64;
+const MAX_HEADER_LENGTH: usize = 4096;
+const MAX_BLOCKS: usize = 1 << 16;
+const MAX_BODY_LENGTH: usize = 1 << 26;
+const MAX_INPUT_LENGTH: usize = 1 << 30;
+const MAX_LOOKAHEAD: usize = 8192;
+
+
+/// A resource a parser bounds.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Limit {
+    /// The number of armor headers in a block.
+    Headers,
+    /// The length in bytes of an armor header value.
+    HeaderLength,
+    /// The number of blocks found in one piece of text.
+    Blocks,
+    /// The number of base64 characters in a body, line endings excluded.
+    BodyLength,
+    /// The number of bytes read from a reader before it is scanned.
+    InputLength,
+    /// The number of tokens the parser holds while it looks ahead.
+    Lookahead
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Limit::Headers => write!(f, "header count"),
+            Limit::HeaderLength => write!(f, "header length"),
+            Limit::Blocks => write!(f, "block count"),
+            Limit::BodyLength => write!(f, "body length"),
+            Limit::InputLength => write!(f, "input length"),
+            Limit::Lookahead => write!(f, "lookahead")
+        }
+    }
+}
+
+/// Bounds on the armor a parser accepts, so that hostile input cannot make it
+/// use unbounded memory. Input past a limit is a `ParseError::LimitExceeded`
+/// error naming the limit.
+///
+/// The lookahead holds the tokens of the longest header line, so it should be
+/// kept above the header length.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Limits {
+    headers: usize,
+    header_length: usize,
+    blocks: usize,
+    body_length: usize,
+    input_length: usize,
+    lookahead: usize
+}
+
+impl Limits {
+    pub fn new() -> Limits {
+        Limits {
+            headers: MAX_HEADERS,
+            header_length: MAX_HEADER_LENGTH,
+            blocks: MAX_BLOCKS,
+            body_length: MAX_BODY_LENGTH,
+            input_length: MAX_INPUT_LENGTH,
+            lookahead: MAX_LOOKAHEAD
+        }
+    }
+
+    /// The most armor headers in a block. Defaults to 64.
+    pub fn max_headers(mut self, headers: usize) -> Limits {
+        self.headers = headers;
+        self
+    }
+
+    /// The longest armor header value, in bytes. Defaults to 4 KiB.
+    pub fn max_header_length(mut self, header_length: usize) -> Limits {
+        self.header_length = header_length;
+        self
+    }
+
+    /// The most blocks a scanner yields. Defaults to 65536. A scanner that
+    /// reaches the limit stops and reports it from `Scanner::error`.
+    pub fn max_blocks(mut self, blocks: usize) -> Limits {
+        self.blocks = blocks;
+        self
+    }
+
+    /// The longest body, in base64 characters. Defaults to 64 MiB, which is
+    /// about 48 MiB of decoded data. Larger bodies, such as backups of big
+    /// keyrings, need a higher limit.
+    pub fn max_body_length(mut self, body_length: usize) -> Limits {
+        self.body_length = body_length;
+        self
+    }
+
+    /// The most bytes read from a reader to scan, such as an mbox file.
+    /// Defaults to 1 GiB.
+    pub fn max_input_length(mut self, input_length: usize) -> Limits {
+        self.input_length = input_length;
+        self
+    }
+
+    /// The most tokens a parser looks ahead by. Defaults to 8192.
+    pub fn max_lookahead(mut self, lookahead: usize) -> Limits {
+        self.lookahead = lookahead;
+        self
+    }
+
+    /// The value of a limit.
+    pub fn limit(&self, limit: Limit) -> usize {
+        match limit {
+            Limit::Headers => self.headers,
+            Limit::HeaderLength => self.header_length,
+            Limit::Blocks => self.blocks,
+            Limit::BodyLength => self.body_length,
+            Limit::InputLength => self.input_length,
+            Limit::Lookahead => self.lookahead
+        }
+    }
+
+    /// Check an amount against a limit.
+    pub fn check(&self, limit: Limit, amount: usize) -> ParseResult<()> {
+        if amount > self.limit(limit) {
+            Err(ParseError::LimitExceeded(limit))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use parser::ParseError;
+    use super::{Limit, Limits};
+
+
+    #[test]
+    fn test_limits() {
+        let limits = Limits::new().max_headers(2).max_body_length(100);
+
+        assert_eq!(limits.limit(Limit::Headers), 2);
+        assert_eq!(limits.limit(Limit::BodyLength), 100);
+        assert_eq!(limits.limit(Limit::Blocks), Limits::default().limit(Limit::Blocks));
+        assert_eq!(limits.check(Limit::Headers, 2), Ok(()));
+        assert_eq!(limits.check(Limit::Headers, 3), Err(ParseError::LimitExceeded(Limit::Headers)));
+    }
+}
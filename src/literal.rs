@@ -0,0 +1,384 @@
+use std::error;
+use std::fmt;
+use inflate::{self, InflateError};
+use packet::{self, PacketTag, PacketError};
+use parser::ArmorMessage;
+
+
+// Compressed Data packets may nest, so bound the nesting to keep a crafted
+// message from recursing without end.
+const MAX_COMPRESSION_DEPTH: usize = 8;
+
+// The most octets the Compressed Data packets of a message may decompress to,
+// all nesting levels together.
+const MAX_DECOMPRESSED_LENGTH: usize = 1 << 26;
+
+
+/// The data format octets of a Literal Data packet, from section 5.9 of RFC9580.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LiteralFormat {
+    Binary,
+    Text,
+    Utf8,
+    Mime,
+    Local,
+    Other(u8)
+}
+
+impl LiteralFormat {
+    pub fn from_u8(format: u8) -> LiteralFormat {
+        match format {
+            b'b' => LiteralFormat::Binary,
+            b't' => LiteralFormat::Text,
+            b'u' => LiteralFormat::Utf8,
+            b'm' => LiteralFormat::Mime,
+            b'l' => LiteralFormat::Local,
+            other => LiteralFormat::Other(other)
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            LiteralFormat::Binary => b'b',
+            LiteralFormat::Text => b't',
+            LiteralFormat::Utf8 => b'u',
+            LiteralFormat::Mime => b'm',
+            LiteralFormat::Local => b'l',
+            LiteralFormat::Other(format) => format
+        }
+    }
+}
+
+/// The compression algorithms from section 9.4 of RFC9580.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CompressionAlgorithm {
+    Uncompressed,
+    Zip,
+    Zlib,
+    Bzip2,
+    Other(u8)
+}
+
+impl CompressionAlgorithm {
+    pub fn from_u8(algorithm: u8) -> CompressionAlgorithm {
+        match algorithm {
+            0 => CompressionAlgorithm::Uncompressed,
+            1 => CompressionAlgorithm::Zip,
+            2 => CompressionAlgorithm::Zlib,
+            3 => CompressionAlgorithm::Bzip2,
+            other => CompressionAlgorithm::Other(other)
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            CompressionAlgorithm::Uncompressed => 0,
+            CompressionAlgorithm::Zip => 1,
+            CompressionAlgorithm::Zlib => 2,
+            CompressionAlgorithm::Bzip2 => 3,
+            CompressionAlgorithm::Other(algorithm) => algorithm
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LiteralError {
+    CorruptBody,
+    MalformedPacket(PacketError),
+    Encrypted(PacketTag),
+    UnexpectedPacket(PacketTag),
+    UnsupportedCompression(CompressionAlgorithm),
+    CorruptCompressedData,
+    DecompressionLimit,
+    NestingTooDeep,
+    TruncatedLiteralData,
+    NoLiteralData
+}
+
+impl fmt::Display for LiteralError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LiteralError::CorruptBody => write!(f, "Corrupt Base64 data."),
+            LiteralError::MalformedPacket(e) => write!(f, "Malformed packet: {}", e),
+            LiteralError::Encrypted(tag) => write!(f, "The message is encrypted in a {:?} packet.", tag),
+            LiteralError::UnexpectedPacket(tag) => write!(f, "Unexpected {:?} packet.", tag),
+            LiteralError::UnsupportedCompression(algorithm) => {
+                write!(f, "Unsupported compression algorithm {}.", algorithm.to_u8())
+            }
+            LiteralError::CorruptCompressedData => write!(f, "Corrupt compressed data."),
+            LiteralError::DecompressionLimit => write!(f, "Compressed data decompresses past the limit."),
+            LiteralError::NestingTooDeep => write!(f, "Compressed data nested too deeply."),
+            LiteralError::TruncatedLiteralData => write!(f, "Truncated literal data packet."),
+            LiteralError::NoLiteralData => write!(f, "The message contains no literal data.")
+        }
+    }
+}
+
+impl error::Error for LiteralError {
+    fn description(&self) -> &str {
+        match *self {
+            LiteralError::CorruptBody => "The Base 64 payload of the armor message was corrupted.",
+            LiteralError::MalformedPacket(_) => "The packet stream in the armor body is malformed.",
+            LiteralError::Encrypted(_) => "The literal data of an encrypted message cannot be read.",
+            LiteralError::UnexpectedPacket(_) => "The message contains a packet that cannot appear in a message.",
+            LiteralError::UnsupportedCompression(_) => "Only uncompressed, ZIP and ZLIB data can be decompressed.",
+            LiteralError::CorruptCompressedData => "The compressed data could not be decompressed.",
+            LiteralError::DecompressionLimit => "The compressed data decompresses to more octets than allowed.",
+            LiteralError::NestingTooDeep => "Compressed Data packets are nested beyond the supported depth.",
+            LiteralError::TruncatedLiteralData => "The Literal Data packet ends inside its header.",
+            LiteralError::NoLiteralData => "The message does not contain a Literal Data packet."
+        }
+    }
+}
+
+pub type LiteralResult<T> = Result<T, LiteralError>;
+
+/// The contents of a Literal Data packet.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LiteralData {
+    format: LiteralFormat,
+    file_name: Vec<u8>,
+    date: u32,
+    content: Vec<u8>
+}
+
+impl LiteralData {
+    pub fn format(&self) -> LiteralFormat {
+        self.format
+    }
+
+    /// The file name octets. The name is not guaranteed to be UTF-8.
+    pub fn file_name(&self) -> &[u8] {
+        &self.file_name
+    }
+
+    /// The modification date in seconds since the Unix epoch, or zero.
+    pub fn date(&self) -> u32 {
+        self.date
+    }
+
+    pub fn content(&self) -> &[u8] {
+        &self.content
+    }
+
+    pub fn into_content(self) -> Vec<u8> {
+        self.content
+    }
+}
+
+fn read_literal(body: &[u8]) -> LiteralResult<LiteralData> {
+    let (&format, rest) = body.split_first().ok_or(LiteralError::TruncatedLiteralData)?;
+    let (&name_length, rest) = rest.split_first().ok_or(LiteralError::TruncatedLiteralData)?;
+    let name_length = name_length as usize;
+    if rest.len() < name_length + 4 {
+        return Err(LiteralError::TruncatedLiteralData);
+    }
+    let (file_name, rest) = rest.split_at(name_length);
+    let (date, content) = rest.split_at(4);
+
+    Ok(LiteralData {
+        format: LiteralFormat::from_u8(format),
+        file_name: file_name.to_vec(),
+        date: u32::from_be_bytes([date[0], date[1], date[2], date[3]]),
+        content: content.to_vec()
+    })
+}
+
+fn decompress(body: &[u8], limit: usize) -> LiteralResult<Vec<u8>> {
+    let (&algorithm, data) = body.split_first().ok_or(LiteralError::CorruptCompressedData)?;
+    let decompressed = match CompressionAlgorithm::from_u8(algorithm) {
+        CompressionAlgorithm::Uncompressed if data.len() > limit => Err(InflateError::OutputLimit),
+        CompressionAlgorithm::Uncompressed => Ok(data.to_vec()),
+        CompressionAlgorithm::Zip => inflate::inflate(data, limit),
+        CompressionAlgorithm::Zlib => inflate::zlib_decompress(data, limit),
+        other => return Err(LiteralError::UnsupportedCompression(other))
+    };
+
+    decompressed.map_err(|e| match e {
+        InflateError::Corrupt => LiteralError::CorruptCompressedData,
+        InflateError::OutputLimit => LiteralError::DecompressionLimit
+    })
+}
+
+// Signed messages wrap the literal data in One-Pass Signature and Signature
+// packets, which are skipped over. Each nesting level may only use what the
+// levels around it left of the limit.
+fn find_literal(data: &[u8], depth: usize, limit: usize) -> LiteralResult<LiteralData> {
+    for packet in packet::packets(data) {
+        let packet = packet.map_err(LiteralError::MalformedPacket)?;
+        match packet.tag() {
+            PacketTag::LiteralData => return read_literal(packet.body()),
+            PacketTag::CompressedData => {
+                if depth == MAX_COMPRESSION_DEPTH {
+                    return Err(LiteralError::NestingTooDeep);
+                }
+                let decompressed = decompress(packet.body(), limit)?;
+                return find_literal(&decompressed, depth + 1, limit - decompressed.len());
+            }
+            PacketTag::OnePassSignature | PacketTag::Signature | PacketTag::Marker | PacketTag::Padding => {}
+            tag @ PacketTag::PublicKeyEncryptedSessionKey
+            | tag @ PacketTag::SymmetricKeyEncryptedSessionKey
+            | tag @ PacketTag::SymmetricallyEncryptedData
+            | tag @ PacketTag::SymEncryptedIntegrityProtectedData
+            | tag @ PacketTag::AeadEncryptedData => return Err(LiteralError::Encrypted(tag)),
+            tag => return Err(LiteralError::UnexpectedPacket(tag))
+        }
+    }
+
+    Err(LiteralError::NoLiteralData)
+}
+
+/// Read the literal data of an unencrypted message from its packet stream,
+/// decompressing Compressed Data packets on the way. Compressed data may
+/// decompress to at most 64 MiB.
+pub fn literal_data(data: &[u8]) -> LiteralResult<LiteralData> {
+    literal_data_with_limit(data, MAX_DECOMPRESSED_LENGTH)
+}
+
+/// Read the literal data of an unencrypted message from its packet stream,
+/// allowing its Compressed Data packets to decompress to at most `limit`
+/// octets in total.
+pub fn literal_data_with_limit(data: &[u8], limit: usize) -> LiteralResult<LiteralData> {
+    find_literal(data, 0, limit)
+}
+
+/// Read the literal data from the body of an unencrypted armor message.
+pub fn message_literal_data(message: &ArmorMessage) -> LiteralResult<LiteralData> {
+    let data = message.decode_secret_body().ok_or(LiteralError::CorruptBody)?;
+    literal_data(&data)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use lexer::Lexer;
+    use parser::Parser;
+    use packet::PacketTag;
+    use super::{LiteralFormat, LiteralError, CompressionAlgorithm};
+
+
+    // The format, file name, date and content of the literal data.
+    type Literal = (LiteralFormat, &'static [u8], u32, &'static [u8]);
+
+    struct TestCase {
+        data: Vec<u8>,
+        result: Result<Literal, LiteralError>
+    }
+
+    struct Test {
+        data: Vec<TestCase>
+    }
+
+    fn literal_test_cases() -> Test {
+        let literal = [0xCB, 0x0C, b't', 0x03, b'a', b'.', b'c', 0x00, 0x00, 0x00, 0x2A, b'h', b'i', b'\n'];
+        let one_pass = [0xC4, 0x0D, 0x03, 0x00, 0x08, 0x16, 0, 0, 0, 0, 0, 0, 0, 0, 0x01];
+        let signature = [0xC2, 0x01, 0x04];
+
+        Test {
+            data: vec![
+                TestCase {
+                    data: literal.to_vec(),
+                    result: Ok((LiteralFormat::Text, b"a.c", 42, b"hi\n"))
+                },
+                TestCase {
+                    data: [&one_pass[..], &literal[..], &signature[..]].concat(),
+                    result: Ok((LiteralFormat::Text, b"a.c", 42, b"hi\n"))
+                },
+                TestCase {
+                    data: [&[0xC8, 0x0F, 0x00][..], &literal[..]].concat(),
+                    result: Ok((LiteralFormat::Text, b"a.c", 42, b"hi\n"))
+                },
+                TestCase {
+                    data: vec![0xC1, 0x01, 0x03],
+                    result: Err(LiteralError::Encrypted(PacketTag::PublicKeyEncryptedSessionKey))
+                },
+                TestCase {
+                    data: vec![0xC8, 0x02, 0x03, 0x00],
+                    result: Err(LiteralError::UnsupportedCompression(CompressionAlgorithm::Bzip2))
+                },
+                TestCase {
+                    data: vec![0xC8, 0x02, 0x02, 0x00],
+                    result: Err(LiteralError::CorruptCompressedData)
+                },
+                TestCase {
+                    data: vec![0xCB, 0x03, b'b', 0x05, b'a'],
+                    result: Err(LiteralError::TruncatedLiteralData)
+                },
+                TestCase {
+                    data: signature.to_vec(),
+                    result: Err(LiteralError::NoLiteralData)
+                },
+                TestCase {
+                    data: vec![0xC6, 0x01, 0x04],
+                    result: Err(LiteralError::UnexpectedPacket(PacketTag::PublicKey))
+                }
+            ]
+        }
+    }
+
+    fn run_tests(test: &Test) {
+        for test_case in test.data.iter() {
+            let result = super::literal_data(&test_case.data);
+            let result = result.as_ref().map(|literal| {
+                (literal.format(), literal.file_name(), literal.date(), literal.content())
+            });
+            assert_eq!(result.map_err(|e| *e), test_case.result);
+        }
+    }
+
+    #[test]
+    fn test_literal_data() {
+        run_tests(&literal_test_cases());
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let mut data = vec![0xCB, 0x06, b'b', 0x00, 0x00, 0x00, 0x00, 0x00];
+        for _ in 0..super::MAX_COMPRESSION_DEPTH + 1 {
+            let mut packet = vec![0xC8, data.len() as u8 + 1, 0x00];
+            packet.extend_from_slice(&data);
+            data = packet;
+        }
+
+        assert_eq!(super::literal_data(&data), Err(LiteralError::NestingTooDeep));
+    }
+
+    #[test]
+    fn test_decompression_limit() {
+        // 87 octets of DEFLATE data that inflate to a Literal Data packet of
+        // 64 KiB of zeros.
+        let compressed = [
+            &[0xC8, 0x58, 0x01][..],
+            &[0xED, 0xC1, 0x31, 0x01, 0x00, 0x30, 0x08, 0x03, 0xB0, 0xF2, 0x4C, 0x28, 0x76, 0x11, 0xC5, 0x2E,
+              0x5C, 0x24, 0x99, 0x4D, 0xE5, 0x75][..],
+            &[0x00; 63][..],
+            &[0xE0, 0x7C][..]
+        ].concat();
+
+        let literal = super::literal_data(&compressed).unwrap();
+        assert_eq!(literal.content().len(), 1 << 16);
+        assert_eq!(super::literal_data_with_limit(&compressed, 1 << 16).map(|_| ()),
+                   Err(LiteralError::DecompressionLimit));
+
+        // An uncompressed layer around it uses up part of the limit.
+        let mut nested = vec![0xC8, 0x5B, 0x00];
+        nested.extend_from_slice(&compressed);
+        let limit = (1 << 16) + 12;
+        assert!(super::literal_data_with_limit(&compressed, limit).is_ok());
+        assert_eq!(super::literal_data_with_limit(&nested, limit).map(|_| ()),
+                   Err(LiteralError::DecompressionLimit));
+    }
+
+    // The ZIP compressed message from `sample/message.asc`.
+    #[test]
+    fn test_sample_message() {
+        let text = include_str!("../sample/message.asc");
+        let message = Parser::new(Lexer::new(text)).parse().unwrap();
+        let literal = super::message_literal_data(&message).unwrap();
+
+        assert_eq!(literal.format(), LiteralFormat::Binary);
+        assert_eq!(literal.file_name(), b"_CONSOLE");
+        assert_eq!(literal.date(), 0);
+        assert_eq!(literal.content(), &b"Can't anyone keep a secret around here?\n"[..]);
+    }
+}
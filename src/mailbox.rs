@@ -0,0 +1,291 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use limits::Limits;
+use mime::MimePart;
+use parser::{ArmorMessage, MessageType, HeaderType};
+use scanner::{self, ArmorBlock};
+
+
+// Multipart entities may nest, so bound the nesting to keep a crafted message
+// from recursing without end. Parts nested deeper are scanned as plain text.
+const MAX_MULTIPART_DEPTH: usize = 16;
+
+/// Where in a mail store an armor block was found.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum MailSource {
+    /// The zero based index of the message in an mbox file.
+    Mbox(usize),
+    /// The file holding the message in a Maildir directory.
+    Maildir(PathBuf)
+}
+
+impl fmt::Display for MailSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MailSource::Mbox(index) => write!(f, "message {}", index),
+            MailSource::Maildir(ref path) => write!(f, "{}", path.display())
+        }
+    }
+}
+
+/// An armor block found in a message of a mail store. The span of the block
+/// refers to the body part it was found in after its transfer encoding has
+/// been undone, not to the raw message.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MailFinding {
+    source: MailSource,
+    block: ArmorBlock,
+    checksum_valid: bool
+}
+
+impl MailFinding {
+    fn new(source: MailSource, block: ArmorBlock) -> MailFinding {
+        let checksum_valid = block.message().has_valid_checksum();
+
+        MailFinding { source, block, checksum_valid }
+    }
+
+    pub fn source(&self) -> &MailSource {
+        &self.source
+    }
+
+    pub fn block(&self) -> &ArmorBlock {
+        &self.block
+    }
+
+    pub fn message(&self) -> &ArmorMessage {
+        self.block.message()
+    }
+
+    pub fn message_type(&self) -> &MessageType {
+        self.block.message().header_type()
+    }
+
+    pub fn headers(&self) -> &[(HeaderType, String)] {
+        self.block.message().header_block()
+    }
+
+    pub fn checksum_valid(&self) -> bool {
+        self.checksum_valid
+    }
+}
+
+// Collect the armor blocks of a message, descending into multipart entities and
+// undoing the transfer encoding of each leaf part. Armor in replies is often
+// quoted, so quote prefixes are stripped.
+fn message_blocks(text: &str, depth: usize, blocks: &mut Vec<ArmorBlock>) {
+    let part = MimePart::parse(text);
+    match part.multipart_bodies() {
+        Some(_) if depth == MAX_MULTIPART_DEPTH => {
+            blocks.extend(scanner::scan(part.body()).strip_quotes(true));
+        }
+        Some(bodies) => {
+            for body in bodies {
+                message_blocks(body, depth + 1, blocks);
+            }
+        }
+        None => {
+            let body = part.decoded_body().unwrap_or_else(|| String::from(part.body()));
+            blocks.extend(scanner::scan(&body).strip_quotes(true));
+        }
+    }
+}
+
+fn scan_message(text: &str, source: MailSource, findings: &mut Vec<MailFinding>) {
+    let mut blocks = Vec::new();
+    message_blocks(text, 0, &mut blocks);
+    findings.extend(blocks.into_iter().map(|block| MailFinding::new(source.clone(), block)));
+}
+
+/// Split an mbox file into its messages. Each message starts with a `From `
+/// line at the start of the file or after an empty line. The `From ` line
+/// itself is not part of the message.
+pub fn mbox_messages(text: &str) -> Vec<&str> {
+    let mut messages = Vec::new();
+    let mut message_start = None;
+    let mut previous_blank = true;
+    let mut position = 0;
+    while position < text.len() {
+        let line_end = text[position..].find('\n').map_or(text.len(), |i| position + i);
+        let next_position = (line_end + 1).min(text.len());
+        let line = &text[position..line_end];
+
+        if previous_blank && line.starts_with("From ") {
+            if let Some(start) = message_start {
+                messages.push(&text[start..position]);
+            }
+            message_start = Some(next_position);
+        }
+
+        previous_blank = line.trim_end_matches('\r').is_empty();
+        position = next_position;
+    }
+
+    if let Some(start) = message_start {
+        messages.push(&text[start..]);
+    }
+
+    messages
+}
+
+/// Scan every message of an mbox file for armor blocks. A file longer than
+/// the default input length limit is an `InvalidData` error.
+pub fn scan_mbox<R: Read>(reader: R) -> io::Result<Vec<MailFinding>> {
+    let octets = scanner::read_input(reader, Limits::default())?;
+    let text = String::from_utf8_lossy(&octets);
+
+    let mut findings = Vec::new();
+    for (index, message) in mbox_messages(&text).into_iter().enumerate() {
+        scan_message(message, MailSource::Mbox(index), &mut findings);
+    }
+
+    Ok(findings)
+}
+
+/// Scan every message in the `new` and `cur` directories of a Maildir for
+/// armor blocks. Messages are visited in file name order.
+pub fn scan_maildir<P: AsRef<Path>>(path: P) -> io::Result<Vec<MailFinding>> {
+    let mut files = Vec::new();
+    for directory in ["new", "cur"].iter() {
+        let directory = path.as_ref().join(directory);
+        if !directory.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(directory)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+    files.sort();
+
+    let mut findings = Vec::new();
+    for file in files {
+        let octets = scanner::read_input(fs::File::open(&file)?, Limits::default())?;
+        let text = String::from_utf8_lossy(&octets);
+        scan_message(&text, MailSource::Maildir(file), &mut findings);
+    }
+
+    Ok(findings)
+}
+
+/// Scan a mail store, which is either a Maildir directory or an mbox file.
+pub fn scan_mail_store<P: AsRef<Path>>(path: P) -> io::Result<Vec<MailFinding>> {
+    if path.as_ref().is_dir() {
+        scan_maildir(path)
+    } else {
+        scan_mbox(fs::File::open(path)?)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::io::Cursor;
+    use parser::{MessageType, HeaderType};
+    use super::MailSource;
+
+
+    fn message_block() -> &'static str {
+        "-----BEGIN PGP MESSAGE-----\n\
+         Version: OpenPrivacy 0.99\n\
+         \n\
+         yDgBO22WxBHv7O8X7O/jygAEzol56iUKiXmV+XmpCtmpqQUKiQrFqclFqUDBovzS\n\
+         vBSFjNSiVHsuAA==\n\
+         =njUN\n\
+         -----END PGP MESSAGE-----\n"
+    }
+
+    fn mbox() -> String {
+        format!("From alice@example.org Mon Oct  5 10:00:00 2026\n\
+                 From: alice@example.org\n\
+                 Subject: plain\n\
+                 \n\
+                 Nothing to see here.\n\
+                 \n\
+                 From bob@example.org Mon Oct  5 11:00:00 2026\n\
+                 From: bob@example.org\n\
+                 Subject: armored\n\
+                 \n\
+                 {block}\n\
+                 From carol@example.org Mon Oct  5 12:00:00 2026\n\
+                 From: carol@example.org\n\
+                 Content-Type: text/plain\n\
+                 Content-Transfer-Encoding: quoted-printable\n\
+                 \n\
+                 > {quoted}\n\
+                 > {broken}\n",
+                block = message_block(),
+                quoted = message_block().trim_end().replace("\n", "\n> ").replace("=", "=3D"),
+                broken = message_block().trim_end().replace("\n", "\n> ").replace("=njUN", "=AAAA").replace("=", "=3D"))
+    }
+
+    #[test]
+    fn test_mbox_messages() {
+        let text = mbox();
+        let messages = super::mbox_messages(&text);
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].starts_with("From: alice@example.org\n"));
+        assert!(messages[1].starts_with("From: bob@example.org\n"));
+        assert!(messages[2].starts_with("From: carol@example.org\n"));
+    }
+
+    #[test]
+    fn test_scan_mbox() {
+        let findings = super::scan_mbox(Cursor::new(mbox().into_bytes())).unwrap();
+        let summary: Vec<_> = findings.iter()
+                                      .map(|finding| (finding.source().clone(), finding.checksum_valid()))
+                                      .collect();
+
+        assert_eq!(summary, vec![
+            (MailSource::Mbox(1), true),
+            (MailSource::Mbox(2), true),
+            (MailSource::Mbox(2), false)
+        ]);
+        for finding in findings.iter() {
+            assert_eq!(finding.message_type(), &MessageType::PGPMessage);
+            assert_eq!(finding.headers(), &[(HeaderType::Version, String::from("OpenPrivacy 0.99"))][..]);
+        }
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let depth = 5000;
+        let mut message = String::from("Subject: nested\n");
+        for i in 0..depth {
+            message.push_str(&format!("Content-Type: multipart/mixed; boundary=\"b{}\"\n\n--b{}\n", i, i));
+        }
+        message.push_str(&format!("\n{}", message_block()));
+        for i in (0..depth).rev() {
+            message.push_str(&format!("--b{}--\n", i));
+        }
+
+        let findings = super::scan_mbox(Cursor::new(format!("From x\n{}", message).into_bytes())).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].checksum_valid());
+    }
+
+    #[test]
+    fn test_scan_maildir() {
+        let maildir = env::temp_dir().join(format!("openpgp-asciiarmor-maildir-{}", ::std::process::id()));
+        for directory in ["new", "cur", "tmp"].iter() {
+            fs::create_dir_all(maildir.join(directory)).unwrap();
+        }
+        fs::write(maildir.join("new").join("1.host"), format!("Subject: a\n\n{}", message_block())).unwrap();
+        fs::write(maildir.join("cur").join("2.host:2,S"), "Subject: b\n\nNo armor.\n").unwrap();
+        fs::write(maildir.join("tmp").join("3.host"), format!("Subject: c\n\n{}", message_block())).unwrap();
+
+        let findings = super::scan_mail_store(&maildir).unwrap();
+        fs::remove_dir_all(&maildir).unwrap();
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].source(), &MailSource::Maildir(maildir.join("new").join("1.host")));
+        assert!(findings[0].checksum_valid());
+    }
+}
@@ -0,0 +1,227 @@
+extern crate openpgp_asciiarmor;
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+use openpgp_asciiarmor::ascii_armor::{self, ArmorBlock, KeyInfo, MailFinding, MailSource, MessageType,
+                                      ParseError, Redactor, SecretFinding, SignatureInfo, UserEntry};
+
+
+const USAGE: &str = "\
+Usage: openpgp-asciiarmor <command> [<args>...]
+
+Commands:
+    scan-mail <path>...       Report the armor blocks in mbox files and Maildir directories
+    inspect <file>...         List the armor blocks in files with their keys, users and signatures
+    scan-secrets <path>...    Report private key blocks in files and directory trees, failing if any
+    redact [--all]            Copy standard input to standard output with private key blocks redacted
+";
+
+
+fn format_finding(path: &str, finding: &MailFinding) -> String {
+    let location = match *finding.source() {
+        MailSource::Mbox(index) => format!("{}#{}", path, index),
+        MailSource::Maildir(ref file) => format!("{}", file.display())
+    };
+    let checksum = if finding.checksum_valid() { "checksum-ok" } else { "checksum-bad" };
+    let headers: Vec<String> = finding.headers()
+                                      .iter()
+                                      .map(|(header_type, value)| {
+                                          format!("{}: {}", ascii_armor::escape_key(header_type.name()),
+                                                  ascii_armor::escape_value(value))
+                                      })
+                                      .collect();
+
+    format!("{}\t{}\t{}\t{}", location, finding.message_type().label(), checksum, headers.join("; "))
+}
+
+// Print one tab separated line per armor block: the message location, the
+// armor label, the checksum status and the armor headers.
+fn scan_mail(paths: &[String]) -> i32 {
+    let mut status = 0;
+    for path in paths {
+        match ascii_armor::scan_mail_store(path) {
+            Ok(findings) => {
+                for finding in findings.iter() {
+                    println!("{}", format_finding(path, finding));
+                }
+            }
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                status = 1;
+            }
+        }
+    }
+
+    status
+}
+
+fn format_key(key: &KeyInfo) -> String {
+    let kind = match (key.is_secret(), key.is_subkey()) {
+        (false, false) => "pub",
+        (false, true) => "sub",
+        (true, false) => "sec",
+        (true, true) => "ssb"
+    };
+
+    format!("    {}  {}  {}  {}", kind, key.algorithm(), key.key_id(), key.fingerprint())
+}
+
+fn format_user(user: &UserEntry) -> String {
+    match *user {
+        UserEntry::UserId { ref id, .. } => format!("    uid  {}", ascii_armor::escape_value(id)),
+        UserEntry::UserAttribute { ref data, .. } => format!("    uat  [{} octets]", data.len())
+    }
+}
+
+fn format_signature(signature: &SignatureInfo) -> String {
+    let issuer = signature.issuer()
+                          .map(|key_id| key_id.to_string())
+                          .or_else(|| signature.issuer_fingerprint().map(|fingerprint| fingerprint.key_id().to_string()))
+                          .unwrap_or_else(|| String::from("unknown"));
+
+    format!("    sig  {:?}  {}  {}  {}", signature.signature_type(), signature.public_key_algorithm(),
+            signature.hash_algorithm(), issuer)
+}
+
+fn inspect_signatures(block: &ArmorBlock) {
+    match ascii_armor::check_signature_block(block.message()) {
+        Ok(signatures) => {
+            for signature in signatures.iter() {
+                println!("{}", format_signature(signature));
+            }
+        }
+        Err(e) => println!("    {}", e)
+    }
+}
+
+fn inspect_block(path: &str, block: &ArmorBlock) {
+    let message = block.message();
+    let checksum = if message.has_valid_checksum() { "checksum-ok" } else { "checksum-bad" };
+    println!("{}:{}\t{}\t{}", path, block.span().start, message.header_type().label(), checksum);
+
+    if *message.header_type() == MessageType::PGPSignature {
+        inspect_signatures(block);
+        return;
+    }
+
+    // List keys and users in packet order so each user follows its key.
+    // Either list is shown even if the other cannot be read.
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    match ascii_armor::message_keys(message) {
+        Ok(keys) => lines.extend(keys.iter().map(|key| (key.offset(), format_key(key)))),
+        Err(e) => println!("    {}", e)
+    }
+    match ascii_armor::message_users(message) {
+        Ok(users) => lines.extend(users.iter().map(|user| (user.offset(), format_user(user)))),
+        Err(e) => println!("    {}", e)
+    }
+    lines.sort();
+    for (_, line) in lines {
+        println!("{}", line);
+    }
+}
+
+// Print a line per armor block with the byte offset of the block, the armor
+// label and the checksum status, followed by the keys and users of key blocks
+// and the signatures of signature blocks.
+fn inspect(paths: &[String]) -> i32 {
+    let mut status = 0;
+    for path in paths {
+        // The blocks found before an error are still listed.
+        let mut blocks = Vec::new();
+        let result = fs::File::open(path).and_then(|file| ascii_armor::scan_reader(file, &mut blocks));
+        for block in blocks.iter() {
+            inspect_block(path, block);
+        }
+        if let Err(e) = result {
+            eprintln!("{}: {}", path, e);
+            status = 1;
+        }
+    }
+
+    status
+}
+
+fn format_secret_finding(finding: &SecretFinding) -> String {
+    let checksum = if finding.checksum_valid() { "checksum-ok" } else { "checksum-bad" };
+    let fingerprints: Vec<String> = finding.fingerprints()
+                                           .iter()
+                                           .map(|fingerprint| fingerprint.to_string().replace(' ', ""))
+                                           .collect();
+
+    format!("{}:{}\t{}\t{}\t{}", finding.path().display(), finding.line(), finding.protection().name(),
+            checksum, fingerprints.join(","))
+}
+
+// Print one tab separated line per private key block: the file and line of
+// the block, whether the keys need a passphrase, the checksum status and the
+// fingerprints of the keys. Any finding fails the run, so the command can be
+// used as a pre-commit hook.
+fn scan_secrets(paths: &[String]) -> i32 {
+    let mut status = 0;
+    for path in paths {
+        let scan = ascii_armor::scan_secret_files(path);
+        for finding in scan.findings().iter() {
+            println!("{}", format_secret_finding(finding));
+            status = 1;
+        }
+        for (file, e) in scan.errors().iter() {
+            eprintln!("{}: {}", file.display(), e);
+            status = 1;
+        }
+    }
+
+    status
+}
+
+fn limit_error(err: ParseError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+fn redact_lines(all_blocks: bool) -> io::Result<()> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+    let mut redactor = Redactor::new().all_blocks(all_blocks);
+
+    for line in stdin.lock().lines() {
+        for redacted in redactor.push_line(&line?).map_err(limit_error)? {
+            writeln!(output, "{}", redacted)?;
+        }
+    }
+    for redacted in redactor.finish().map_err(limit_error)? {
+        writeln!(output, "{}", redacted)?;
+    }
+
+    Ok(())
+}
+
+// Redact standard input line by line, so the command can sit in a log pipeline.
+fn redact(all_blocks: bool) -> i32 {
+    match redact_lines(all_blocks) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("redact: {}", e);
+            1
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let status = match args.first().map(String::as_str) {
+        Some("scan-mail") if args.len() > 1 => scan_mail(&args[1..]),
+        Some("inspect") if args.len() > 1 => inspect(&args[1..]),
+        Some("scan-secrets") if args.len() > 1 => scan_secrets(&args[1..]),
+        Some("redact") if args.len() == 1 => redact(false),
+        Some("redact") if args.len() == 2 && args[1] == "--all" => redact(true),
+        _ => {
+            eprint!("{}", USAGE);
+            2
+        }
+    };
+
+    process::exit(status);
+}
@@ -0,0 +1,466 @@
+use base64;
+
+
+/// The Content-Transfer-Encoding of a MIME part, from section 6 of RFC2045.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TransferEncoding {
+    SevenBit,
+    EightBit,
+    Binary,
+    QuotedPrintable,
+    Base64,
+    OtherEncoding(String)
+}
+
+impl TransferEncoding {
+    pub fn from_header(value: &str) -> TransferEncoding {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "7bit" => TransferEncoding::SevenBit,
+            "8bit" => TransferEncoding::EightBit,
+            "binary" => TransferEncoding::Binary,
+            "quoted-printable" => TransferEncoding::QuotedPrintable,
+            "base64" => TransferEncoding::Base64,
+            other => TransferEncoding::OtherEncoding(String::from(other))
+        }
+    }
+}
+
+/// A Content-Type header field value, as described in section 5 of RFC2045.
+/// The media type is kept in lower case.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ContentType {
+    mime_type: String,
+    parameters: Vec<(String, String)>
+}
+
+impl ContentType {
+    pub fn parse(value: &str) -> ContentType {
+        let mut fields = split_parameters(value).into_iter();
+        let mime_type = fields.next().unwrap_or_default().to_ascii_lowercase();
+
+        let mut parameters = Vec::new();
+        for field in fields {
+            if let Some(equals) = field.find('=') {
+                let name = String::from(field[..equals].trim());
+                let value = unquote_parameter(field[equals + 1..].trim());
+                parameters.push((name, value));
+            }
+        }
+
+        ContentType { mime_type, parameters }
+    }
+
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    /// Look up a parameter by name. Parameter names are case insensitive.
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters.iter()
+                       .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                       .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_multipart(&self) -> bool {
+        self.mime_type.starts_with("multipart/")
+    }
+}
+
+// Split a header field value on the semicolons that are not inside a quoted string.
+fn split_parameters(value: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for ch in value.chars() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                fields.push(String::from(field.trim()));
+                field.clear();
+                continue;
+            }
+            _ => {}
+        }
+        field.push(ch);
+    }
+    fields.push(String::from(field.trim()));
+
+    fields
+}
+
+fn unquote_parameter(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) {
+        Some(quoted) => {
+            let mut result = String::with_capacity(quoted.len());
+            let mut chars = quoted.chars();
+            while let Some(ch) = chars.next() {
+                match ch {
+                    '\\' => result.extend(chars.next()),
+                    _ => result.push(ch)
+                }
+            }
+            result
+        }
+        None => String::from(value)
+    }
+}
+
+/// A MIME part split into its header fields and its raw body.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MimePart<'a> {
+    headers: Vec<(String, String)>,
+    body: &'a str
+}
+
+impl<'a> MimePart<'a> {
+    /// Split a MIME part at the first empty line. Folded header lines are
+    /// unfolded. Text that does not start with a header section is treated as
+    /// a part with no headers.
+    pub fn parse(text: &'a str) -> MimePart<'a> {
+        let mut headers: Vec<(String, String)> = Vec::new();
+        let mut position = 0;
+        while position < text.len() {
+            let line_end = text[position..].find('\n').map_or(text.len(), |i| position + i);
+            let next_position = (line_end + 1).min(text.len());
+            let line = text[position..line_end].trim_end_matches('\r');
+
+            if line.is_empty() {
+                return MimePart { headers, body: &text[next_position..] };
+            }
+
+            if line.starts_with(' ') || line.starts_with('\t') {
+                match headers.last_mut() {
+                    Some((_, value)) => {
+                        value.push(' ');
+                        value.push_str(line.trim());
+                    }
+                    None => break
+                }
+            } else {
+                match line.find(':') {
+                    Some(colon) if is_field_name(&line[..colon]) => {
+                        let name = String::from(&line[..colon]);
+                        let value = String::from(line[colon + 1..].trim());
+                        headers.push((name, value));
+                    }
+                    _ => break
+                }
+            }
+
+            position = next_position;
+        }
+
+        if position >= text.len() && !headers.is_empty() {
+            MimePart { headers, body: &text[text.len()..] }
+        } else {
+            MimePart { headers: Vec::new(), body: text }
+        }
+    }
+
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Look up a header field by name. Field names are case insensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                    .map(|(_, value)| value.as_str())
+    }
+
+    pub fn body(&self) -> &'a str {
+        self.body
+    }
+
+    /// The content type of the part. A part without a Content-Type field is
+    /// plain text.
+    pub fn content_type(&self) -> ContentType {
+        ContentType::parse(self.header("Content-Type").unwrap_or("text/plain"))
+    }
+
+    /// Split the body of a multipart entity into its body parts using the
+    /// boundary parameter of its content type. Returns `None` when the part is
+    /// not a multipart entity or its boundary never occurs in the body.
+    pub fn multipart_bodies(&self) -> Option<Vec<&'a str>> {
+        let content_type = self.content_type();
+        if !content_type.is_multipart() {
+            return None;
+        }
+
+        split_multipart(self.body, content_type.parameter("boundary")?)
+    }
+
+    /// The transfer encoding of the part. A part without a
+    /// Content-Transfer-Encoding field is 7bit.
+    pub fn transfer_encoding(&self) -> TransferEncoding {
+        self.header("Content-Transfer-Encoding")
+            .map_or(TransferEncoding::SevenBit, TransferEncoding::from_header)
+    }
+
+    /// Undo the transfer encoding of the body. Quoted-printable is also undone
+    /// when the part does not declare it but the body contains encoded armor.
+    /// Returns `None` when a base64 body cannot be decoded into text.
+    pub fn decoded_body(&self) -> Option<String> {
+        match self.transfer_encoding() {
+            TransferEncoding::QuotedPrintable => Some(decode_quoted_printable(self.body)),
+            TransferEncoding::Base64 => decode_base64_text(self.body),
+            _ => {
+                if is_quoted_printable(self.body) {
+                    Some(decode_quoted_printable(self.body))
+                } else {
+                    Some(String::from(self.body))
+                }
+            }
+        }
+    }
+}
+
+// Split a multipart body on its delimiter lines, following section 5.1.1 of
+// RFC2046. The line break in front of a delimiter line belongs to the delimiter.
+// The preamble and epilogue are dropped, and a missing close delimiter is
+// tolerated.
+fn split_multipart<'a>(body: &'a str, boundary: &str) -> Option<Vec<&'a str>> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = Vec::new();
+    let mut part_start = None;
+    let mut position = 0;
+    loop {
+        let line_end = body[position..].find('\n').map_or(body.len(), |i| position + i);
+        let line = body[position..line_end].trim_end();
+
+        if let Some(rest) = line.strip_prefix(delimiter.as_str()) {
+            let is_close_delimiter = rest.starts_with("--");
+            if rest.is_empty() || is_close_delimiter {
+                if let Some(start) = part_start {
+                    let preceding = &body[start..position];
+                    let end = preceding.strip_suffix('\n')
+                                       .map(|text| text.strip_suffix('\r').unwrap_or(text))
+                                       .map_or(position, |text| start + text.len());
+                    parts.push(&body[start..end]);
+                }
+                if is_close_delimiter {
+                    return Some(parts);
+                }
+                part_start = Some((line_end + 1).min(body.len()));
+            }
+        }
+
+        if line_end >= body.len() {
+            break;
+        }
+        position = line_end + 1;
+    }
+
+    part_start.map(|start| {
+        parts.push(&body[start..]);
+        parts
+    })
+}
+
+fn is_field_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|byte| byte > b' ' && byte < 0x7F && byte != b':')
+}
+
+// Quoted-printable encoding turns every pad character of the armor into `=3D`.
+// A checksum line of plain armor can legitimately start with `=3D`, but it is
+// then exactly five characters long.
+fn is_quoted_printable(text: &str) -> bool {
+    text.lines()
+        .map(|line| line.trim_end())
+        .any(|line| line.contains("=3D") && !(line.len() == 5 && line.starts_with("=3D")))
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        _ => None
+    }
+}
+
+/// Decode quoted-printable text as described in section 6.7 of RFC2045. Soft
+/// line breaks are removed and `=XX` escapes are replaced by the octets they
+/// encode. Malformed escapes are kept as they are.
+pub fn decode_quoted_printable(text: &str) -> String {
+    let mut result = Vec::with_capacity(text.len());
+    let mut lines = text.split('\n').peekable();
+    while let Some(line) = lines.next() {
+        // Trailing whitespace was added in transport and is not part of the data.
+        let line = line.trim_end_matches(['\r', ' ', '\t']);
+        let (line, soft_break) = match line.strip_suffix('=') {
+            Some(rest) => (rest, true),
+            None => (line, false)
+        };
+
+        let bytes = line.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'=' && i + 2 < bytes.len() {
+                if let (Some(high), Some(low)) = (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                    result.push((high << 4) | low);
+                    i += 3;
+                    continue;
+                }
+            }
+            result.push(bytes[i]);
+            i += 1;
+        }
+
+        if !soft_break && lines.peek().is_some() {
+            result.push(b'\n');
+        }
+    }
+
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+fn decode_base64_text(text: &str) -> Option<String> {
+    let stripped: String = text.chars().filter(|ch| !ch.is_whitespace()).collect();
+    base64::decode(&stripped).and_then(|octets| String::from_utf8(octets).ok())
+}
+
+/// Undo the transfer encoding of a MIME part, headers included, so that the
+/// armor in its body can be handed to the parser.
+pub fn decode_part(text: &str) -> Option<String> {
+    MimePart::parse(text).decoded_body()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use scanner;
+    use super::{MimePart, ContentType, TransferEncoding};
+
+
+    fn message_block() -> &'static str {
+        "-----BEGIN PGP MESSAGE-----\n\
+         Version: OpenPrivacy 0.99\n\
+         \n\
+         yDgBO22WxBHv7O8X7O/jygAEzol56iUKiXmV+XmpCtmpqQUKiQrFqclFqUDBovzS\n\
+         vBSFjNSiVHsuAA==\n\
+         =njUN\n\
+         -----END PGP MESSAGE-----\n"
+    }
+
+    fn quoted_printable_block() -> &'static str {
+        "-----BEGIN PGP MESSAGE-----\r\n\
+         Version: OpenPrivacy 0.99\r\n\
+         \r\n\
+         yDgBO22WxBHv7O8X7O/jygAEzol56iUKiXmV+XmpCtmpqQUKiQrFqclFqUDBov=\r\n\
+         zS\r\n\
+         vBSFjNSiVHsuAA=3D=3D\r\n\
+         =3DnjUN\r\n\
+         -----END PGP MESSAGE-----\r\n"
+    }
+
+    struct TestCase {
+        part: String,
+        transfer_encoding: TransferEncoding
+    }
+
+    struct Test {
+        data: Vec<TestCase>
+    }
+
+    fn decode_part_test_cases() -> Test {
+        Test {
+            data: vec![
+                TestCase {
+                    part: String::from(message_block()),
+                    transfer_encoding: TransferEncoding::SevenBit
+                },
+                TestCase {
+                    part: format!("Content-Type: text/plain; charset=us-ascii\r\n\
+                                   Content-Transfer-Encoding: quoted-printable\r\n\r\n{}",
+                                  quoted_printable_block()),
+                    transfer_encoding: TransferEncoding::QuotedPrintable
+                },
+                TestCase {
+                    part: format!("Content-Type: text/plain\n\n{}", quoted_printable_block()),
+                    transfer_encoding: TransferEncoding::SevenBit
+                },
+                TestCase {
+                    part: String::from(
+                        "Content-Type: application/pgp-encrypted;\n\
+                         \tname=\"message.asc\"\n\
+                         Content-Transfer-Encoding: BASE64\n\
+                         \n\
+                         LS0tLS1CRUdJTiBQR1AgTUVTU0FHRS0tLS0tClZlcnNpb246IE9wZW5Qcml2YWN5IDAuOTkKCnlE\n\
+                         Z0JPMjJXeEJIdjdPOFg3Ty9qeWdBRXpvbDU2aVVLaVhtVitYbXBDdG1wcVFVS2lRckZxY2xGcVVE\n\
+                         Qm92elMKdkJTRmpOU2lWSHN1QUE9PQo9bmpVTgotLS0tLUVORCBQR1AgTUVTU0FHRS0tLS0tCg==\n"),
+                    transfer_encoding: TransferEncoding::Base64
+                }
+            ]
+        }
+    }
+
+    fn run_tests(test: &Test) {
+        for test_case in test.data.iter() {
+            let part = MimePart::parse(&test_case.part);
+            assert_eq!(part.transfer_encoding(), test_case.transfer_encoding);
+
+            let decoded = super::decode_part(&test_case.part).unwrap();
+            let blocks: Vec<_> = scanner::scan(&decoded).collect();
+            assert_eq!(blocks.len(), 1);
+            assert_eq!(blocks[0].message().body(),
+                       "yDgBO22WxBHv7O8X7O/jygAEzol56iUKiXmV+XmpCtmpqQUKiQrFqclFqUDBovzSvBSFjNSiVHsuAA==");
+            assert_eq!(blocks[0].message().checksum(), "njUN");
+        }
+    }
+
+    #[test]
+    fn test_decode_part() {
+        run_tests(&decode_part_test_cases());
+    }
+
+    #[test]
+    fn test_mime_part_headers() {
+        let part = MimePart::parse("Content-Type: multipart/signed;\r\n \
+                                    micalg=pgp-sha256\r\ncontent-transfer-encoding: 7bit\r\n\r\nbody");
+        assert_eq!(part.header("content-type"), Some("multipart/signed; micalg=pgp-sha256"));
+        assert_eq!(part.header("Content-Transfer-Encoding"), Some("7bit"));
+        assert_eq!(part.body(), "body");
+    }
+
+    #[test]
+    fn test_content_type() {
+        let content_type = ContentType::parse("Multipart/Signed; micalg=pgp-sha256;\
+                                               protocol=\"application/pgp-signature\"; \
+                                               boundary=\"a;b\\\"c\"");
+        assert_eq!(content_type.mime_type(), "multipart/signed");
+        assert!(content_type.is_multipart());
+        assert_eq!(content_type.parameter("MICALG"), Some("pgp-sha256"));
+        assert_eq!(content_type.parameter("protocol"), Some("application/pgp-signature"));
+        assert_eq!(content_type.parameter("boundary"), Some("a;b\"c"));
+        assert_eq!(content_type.parameter("charset"), None);
+    }
+
+    #[test]
+    fn test_multipart_bodies() {
+        let part = MimePart::parse("Content-Type: multipart/mixed; boundary=XYZ\r\n\
+                                    \r\n\
+                                    preamble\r\n\
+                                    --XYZ\r\n\
+                                    Content-Type: text/plain\r\n\
+                                    \r\n\
+                                    first\r\n\
+                                    --XYZ \r\n\
+                                    \r\n\
+                                    second\r\n\
+                                    \r\n\
+                                    --XYZ--\r\n\
+                                    epilogue\r\n");
+        assert_eq!(part.multipart_bodies(),
+                   Some(vec!["Content-Type: text/plain\r\n\r\nfirst", "\r\nsecond\r\n"]));
+        assert_eq!(MimePart::parse("Content-Type: text/plain\n\n--XYZ\n").multipart_bodies(), None);
+    }
+
+    #[test]
+    fn test_decode_quoted_printable() {
+        assert_eq!(super::decode_quoted_printable("a=3Db=\r\nc=20  \r\nd=4"), "a=bc \nd=4");
+    }
+}
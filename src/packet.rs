@@ -0,0 +1,443 @@
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+
+
+/// The packet tags from section 4.3 of RFC4880 and section 5 of RFC9580.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PacketTag {
+    Reserved,
+    PublicKeyEncryptedSessionKey,
+    Signature,
+    SymmetricKeyEncryptedSessionKey,
+    OnePassSignature,
+    SecretKey,
+    PublicKey,
+    SecretSubkey,
+    CompressedData,
+    SymmetricallyEncryptedData,
+    Marker,
+    LiteralData,
+    Trust,
+    UserID,
+    PublicSubkey,
+    UserAttribute,
+    SymEncryptedIntegrityProtectedData,
+    ModificationDetectionCode,
+    AeadEncryptedData,
+    Padding,
+    Other(u8)
+}
+
+impl PacketTag {
+    pub fn from_u8(tag: u8) -> PacketTag {
+        match tag {
+            0  => PacketTag::Reserved,
+            1  => PacketTag::PublicKeyEncryptedSessionKey,
+            2  => PacketTag::Signature,
+            3  => PacketTag::SymmetricKeyEncryptedSessionKey,
+            4  => PacketTag::OnePassSignature,
+            5  => PacketTag::SecretKey,
+            6  => PacketTag::PublicKey,
+            7  => PacketTag::SecretSubkey,
+            8  => PacketTag::CompressedData,
+            9  => PacketTag::SymmetricallyEncryptedData,
+            10 => PacketTag::Marker,
+            11 => PacketTag::LiteralData,
+            12 => PacketTag::Trust,
+            13 => PacketTag::UserID,
+            14 => PacketTag::PublicSubkey,
+            17 => PacketTag::UserAttribute,
+            18 => PacketTag::SymEncryptedIntegrityProtectedData,
+            19 => PacketTag::ModificationDetectionCode,
+            20 => PacketTag::AeadEncryptedData,
+            21 => PacketTag::Padding,
+            other => PacketTag::Other(other)
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            PacketTag::Reserved => 0,
+            PacketTag::PublicKeyEncryptedSessionKey => 1,
+            PacketTag::Signature => 2,
+            PacketTag::SymmetricKeyEncryptedSessionKey => 3,
+            PacketTag::OnePassSignature => 4,
+            PacketTag::SecretKey => 5,
+            PacketTag::PublicKey => 6,
+            PacketTag::SecretSubkey => 7,
+            PacketTag::CompressedData => 8,
+            PacketTag::SymmetricallyEncryptedData => 9,
+            PacketTag::Marker => 10,
+            PacketTag::LiteralData => 11,
+            PacketTag::Trust => 12,
+            PacketTag::UserID => 13,
+            PacketTag::PublicSubkey => 14,
+            PacketTag::UserAttribute => 17,
+            PacketTag::SymEncryptedIntegrityProtectedData => 18,
+            PacketTag::ModificationDetectionCode => 19,
+            PacketTag::AeadEncryptedData => 20,
+            PacketTag::Padding => 21,
+            PacketTag::Other(tag) => tag
+        }
+    }
+}
+
+/// The two packet header formats from section 4.2 of RFC4880.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PacketFormat {
+    Old,
+    New
+}
+
+/// A single OpenPGP packet. The offsets are relative to the start of the
+/// packet stream. A body sent with partial body lengths is split in chunks
+/// in the stream, so it is reassembled into an owned buffer.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Packet<'a> {
+    tag: PacketTag,
+    format: PacketFormat,
+    offset: usize,
+    body_offset: usize,
+    length: usize,
+    body: Cow<'a, [u8]>
+}
+
+impl<'a> Packet<'a> {
+    pub fn tag(&self) -> PacketTag {
+        self.tag
+    }
+
+    pub fn format(&self) -> PacketFormat {
+        self.format
+    }
+
+    /// The offset of the first octet of the packet header.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The offset of the first octet of the packet body.
+    pub fn body_offset(&self) -> usize {
+        self.body_offset
+    }
+
+    /// The number of octets the packet occupies in the stream, headers and
+    /// partial length octets included.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    pub fn into_body(self) -> Cow<'a, [u8]> {
+        self.body
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PacketError {
+    InvalidHeader,
+    TruncatedHeader,
+    TruncatedBody
+}
+
+impl fmt::Display for PacketError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PacketError::InvalidHeader => write!(f, "Invalid packet header."),
+            PacketError::TruncatedHeader => write!(f, "Truncated packet header."),
+            PacketError::TruncatedBody => write!(f, "Truncated packet body.")
+        }
+    }
+}
+
+impl error::Error for PacketError {
+    fn description(&self) -> &str {
+        match *self {
+            PacketError::InvalidHeader => "The first octet of the packet header does not have its high bit set.",
+            PacketError::TruncatedHeader => "The packet stream ends in the middle of a packet header.",
+            PacketError::TruncatedBody => "The packet stream ends before the end of the packet body."
+        }
+    }
+}
+
+pub type PacketResult<T> = Result<T, PacketError>;
+
+// A body length from a new format packet header.
+enum BodyLength {
+    Full(usize),
+    Partial(usize)
+}
+
+/// An iterator over the packets of a packet stream, such as the decoded body
+/// of an armor message. Iteration stops after the first error.
+pub struct Packets<'a> {
+    data: &'a [u8],
+    position: usize,
+    failed: bool
+}
+
+impl<'a> Packets<'a> {
+    pub fn new(data: &'a [u8]) -> Packets<'a> {
+        Packets {
+            data,
+            position: 0,
+            failed: false
+        }
+    }
+
+    fn read_octet(&mut self) -> PacketResult<u8> {
+        match self.data.get(self.position) {
+            Some(&octet) => {
+                self.position += 1;
+                Ok(octet)
+            }
+            None => Err(PacketError::TruncatedHeader)
+        }
+    }
+
+    fn read_length(&mut self, octets: usize) -> PacketResult<usize> {
+        let mut length = 0;
+        for _ in 0..octets {
+            length = (length << 8) | self.read_octet()? as usize;
+        }
+
+        Ok(length)
+    }
+
+    fn read_body(&mut self, length: usize) -> PacketResult<&'a [u8]> {
+        let data = self.data;
+        if length > data.len() - self.position {
+            return Err(PacketError::TruncatedBody);
+        }
+        let body = &data[self.position..self.position + length];
+        self.position += length;
+
+        Ok(body)
+    }
+
+    // Section 4.2.2 of RFC4880.
+    fn read_new_length(&mut self) -> PacketResult<BodyLength> {
+        let first = self.read_octet()? as usize;
+        match first {
+            0..=191 => Ok(BodyLength::Full(first)),
+            192..=223 => {
+                let second = self.read_octet()? as usize;
+                Ok(BodyLength::Full(((first - 192) << 8) + second + 192))
+            }
+            255 => self.read_length(4).map(BodyLength::Full),
+            _ => Ok(BodyLength::Partial(1 << (first & 0x1F)))
+        }
+    }
+
+    // Returns the offset of the first body octet along with the body.
+    fn read_new_body(&mut self) -> PacketResult<(usize, Cow<'a, [u8]>)> {
+        match self.read_new_length()? {
+            BodyLength::Full(length) => {
+                let body_offset = self.position;
+                self.read_body(length).map(|body| (body_offset, Cow::Borrowed(body)))
+            }
+            BodyLength::Partial(length) => {
+                let body_offset = self.position;
+                let mut body = self.read_body(length)?.to_vec();
+                loop {
+                    match self.read_new_length()? {
+                        BodyLength::Full(length) => {
+                            body.extend_from_slice(self.read_body(length)?);
+                            return Ok((body_offset, Cow::Owned(body)));
+                        }
+                        BodyLength::Partial(length) => {
+                            body.extend_from_slice(self.read_body(length)?);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Section 4.2.1 of RFC4880. A length type of 3 means the packet extends to
+    // the end of the stream.
+    fn read_old_body(&mut self, length_type: u8) -> PacketResult<(usize, Cow<'a, [u8]>)> {
+        let length = match length_type {
+            0 => self.read_length(1)?,
+            1 => self.read_length(2)?,
+            2 => self.read_length(4)?,
+            _ => self.data.len() - self.position
+        };
+
+        let body_offset = self.position;
+        self.read_body(length).map(|body| (body_offset, Cow::Borrowed(body)))
+    }
+
+    fn read_packet(&mut self) -> PacketResult<Packet<'a>> {
+        let offset = self.position;
+        let header = self.read_octet()?;
+        let tag = header_tag(header)?;
+
+        let (format, (body_offset, body)) = if header & 0x40 != 0 {
+            (PacketFormat::New, self.read_new_body()?)
+        } else {
+            (PacketFormat::Old, self.read_old_body(header & 0x03)?)
+        };
+
+        Ok(Packet {
+            tag,
+            format,
+            offset,
+            body_offset,
+            length: self.position - offset,
+            body
+        })
+    }
+}
+
+impl<'a> Iterator for Packets<'a> {
+    type Item = PacketResult<Packet<'a>>;
+
+    fn next(&mut self) -> Option<PacketResult<Packet<'a>>> {
+        if self.failed || self.position >= self.data.len() {
+            return None;
+        }
+
+        let result = self.read_packet();
+        if result.is_err() {
+            self.failed = true;
+        }
+
+        Some(result)
+    }
+}
+
+/// The tag in the first octet of a packet header, in either format.
+pub fn header_tag(header: u8) -> PacketResult<PacketTag> {
+    if header & 0x80 == 0 {
+        Err(PacketError::InvalidHeader)
+    } else if header & 0x40 != 0 {
+        Ok(PacketTag::from_u8(header & 0x3F))
+    } else {
+        Ok(PacketTag::from_u8((header >> 2) & 0x0F))
+    }
+}
+
+/// Iterate over the packets of a packet stream.
+pub fn packets(data: &[u8]) -> Packets<'_> {
+    Packets::new(data)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use lexer::Lexer;
+    use parser::Parser;
+    use super::{PacketTag, PacketFormat, PacketError};
+
+
+    struct TestCase {
+        data: Vec<u8>,
+        packets: Vec<(PacketTag, PacketFormat, usize, usize, Vec<u8>)>
+    }
+
+    struct Test {
+        data: Vec<TestCase>
+    }
+
+    fn packet_test_cases() -> Test {
+        let long_body: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let mut partial = vec![0xCB, 0xE9];
+        partial.extend_from_slice(&long_body[..512]);
+        partial.extend_from_slice(&[0xE0]);
+        partial.extend_from_slice(&long_body[512..513]);
+        partial.extend_from_slice(&[0xC1, 0x27]);
+        partial.extend_from_slice(&long_body[513..1000]);
+
+        Test {
+            data: vec![
+                TestCase {
+                    data: vec![0xB4, 0x03, b'a', b'b', b'c', 0xCD, 0x02, b'x', b'y'],
+                    packets: vec![
+                        (PacketTag::UserID, PacketFormat::Old, 0, 2, b"abc".to_vec()),
+                        (PacketTag::UserID, PacketFormat::New, 5, 7, b"xy".to_vec())
+                    ]
+                },
+                TestCase {
+                    data: {
+                        let mut data = vec![0x99, 0x03, 0xE8];
+                        data.extend_from_slice(&long_body);
+                        data.extend_from_slice(&[0xC2, 0xFF, 0x00, 0x00, 0x00, 0x01, 0x04]);
+                        data.extend_from_slice(&[0xC2, 0xC3, 0x28]);
+                        data.extend_from_slice(&long_body);
+                        data
+                    },
+                    packets: vec![
+                        (PacketTag::PublicKey, PacketFormat::Old, 0, 3, long_body.clone()),
+                        (PacketTag::Signature, PacketFormat::New, 1003, 1009, vec![0x04]),
+                        (PacketTag::Signature, PacketFormat::New, 1010, 1013, long_body.clone())
+                    ]
+                },
+                TestCase {
+                    data: partial,
+                    packets: vec![(PacketTag::LiteralData, PacketFormat::New, 0, 2, long_body.clone())]
+                },
+                TestCase {
+                    data: vec![0xA3, 0x01, 0x02, 0x03],
+                    packets: vec![(PacketTag::CompressedData, PacketFormat::Old, 0, 1, vec![0x01, 0x02, 0x03])]
+                }
+            ]
+        }
+    }
+
+    fn run_tests(test: &Test) {
+        for test_case in test.data.iter() {
+            let packets: Vec<_> = super::packets(&test_case.data).map(|packet| packet.unwrap()).collect();
+            assert_eq!(packets.len(), test_case.packets.len());
+            for (packet, expected) in packets.iter().zip(test_case.packets.iter()) {
+                assert_eq!(packet.tag(), expected.0);
+                assert_eq!(packet.format(), expected.1);
+                assert_eq!(packet.offset(), expected.2);
+                assert_eq!(packet.body_offset(), expected.3);
+                assert_eq!(packet.body(), &expected.4[..]);
+            }
+
+            let length: usize = packets.iter().map(|packet| packet.length()).sum();
+            assert_eq!(length, test_case.data.len());
+        }
+    }
+
+    #[test]
+    fn test_packets() {
+        run_tests(&packet_test_cases());
+    }
+
+    #[test]
+    fn test_packet_errors() {
+        let errors = vec![
+            (vec![0x34, 0x00], PacketError::InvalidHeader),
+            (vec![0xCD], PacketError::TruncatedHeader),
+            (vec![0xCD, 0xC5], PacketError::TruncatedHeader),
+            (vec![0xCD, 0x05, b'a'], PacketError::TruncatedBody),
+            (vec![0xCB, 0xE1, b'a', b'b'], PacketError::TruncatedHeader)
+        ];
+
+        for (data, error) in errors {
+            let results: Vec<_> = super::packets(&data).collect();
+            assert_eq!(results, vec![Err(error)]);
+        }
+    }
+
+    #[test]
+    fn test_packets_sample_public_key() {
+        let text = include_str!("../sample/pub.asc");
+        let message = Parser::new(Lexer::new(text)).parse().unwrap();
+        let body = message.decode_body().unwrap();
+        let packets: Vec<_> = super::packets(&body).map(|packet| packet.unwrap()).collect();
+
+        assert_eq!(packets[0].tag(), PacketTag::PublicKey);
+        assert_eq!(packets[0].body()[0], 4);
+        assert!(packets.iter().any(|packet| packet.tag() == PacketTag::UserID));
+        assert!(packets.iter().any(|packet| packet.tag() == PacketTag::PublicSubkey));
+        assert_eq!(packets.iter().map(|packet| packet.length()).sum::<usize>(), body.len());
+    }
+}
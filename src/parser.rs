@@ -1,11 +1,15 @@
 #![allow(dead_code)]
 use std::collections::VecDeque;
-use std::iter::Peekable;
 use lexer::Lexer;
-use token::{Token, TokenType};
+use token::{Span, Token, TokenType};
 use base64::Base64;
 use base64;
 use crc24;
+use body::{BodyDecoder, DecodedBody};
+use encoder::{encode, encode_redacted};
+use zeroize::{self, SecretBytes};
+use visitor::{Location, Visitor};
+use limits::{Limit, Limits};
 use std::error;
 use std::fmt;
 
@@ -33,6 +37,39 @@ pub enum HeaderType {
     OtherHeader(String)
 }
 
+impl MessageType {
+    /// The text between `BEGIN `/`END ` and the closing dashes of the header
+    /// and tail lines.
+    pub fn label(&self) -> String {
+        match *self {
+            MessageType::PGPMessage => String::from(TokenType::PGPMessage.armor_string().unwrap()),
+            MessageType::PGPPublicKeyBlock => String::from(TokenType::PGPPublicKeyBlock.armor_string().unwrap()),
+            MessageType::PGPPrivateKeyBlock => String::from(TokenType::PGPPrivateKeyBlock.armor_string().unwrap()),
+            MessageType::PGPSignature => String::from(TokenType::PGPSignature.armor_string().unwrap()),
+            MessageType::PGPMessagePartXofY(x, y) => {
+                format!("{}{}/{}", TokenType::PGPMessagePart.armor_string().unwrap(), x, y)
+            }
+            MessageType::PGPMessagePartX(x) => {
+                format!("{}{}", TokenType::PGPMessagePart.armor_string().unwrap(), x)
+            }
+        }
+    }
+}
+
+impl HeaderType {
+    /// The key of an armor header line with this header type.
+    pub fn name(&self) -> &str {
+        match *self {
+            HeaderType::Version => TokenType::Version.armor_string().unwrap(),
+            HeaderType::Comment => TokenType::Comment.armor_string().unwrap(),
+            HeaderType::MessageID => TokenType::MessageID.armor_string().unwrap(),
+            HeaderType::Hash => TokenType::Hash.armor_string().unwrap(),
+            HeaderType::Charset => TokenType::Charset.armor_string().unwrap(),
+            HeaderType::OtherHeader(ref name) => name.as_str()
+        }
+    }
+}
+
 fn token_type_to_header_type(token_type: TokenType) -> HeaderType {
     match token_type {
         TokenType::Version   => HeaderType::Version,
@@ -56,6 +93,7 @@ struct Body {
     checksum: crc24::Crc24
 }
 
+#[derive(Clone, PartialEq, Eq)]
 pub struct ArmorMessage {
     header_type: MessageType,
     header_block: Vec<(HeaderType, String)>,
@@ -76,6 +114,95 @@ impl ArmorMessage {
             checksum: checksum
         }
     }
+
+    pub fn header_type(&self) -> &MessageType {
+        &self.header_type
+    }
+
+    pub fn header_block(&self) -> &[(HeaderType, String)] {
+        &self.header_block
+    }
+
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    pub fn checksum(&self) -> &str {
+        &self.checksum
+    }
+
+    /// Tests whether the message is a private key block, whose body is
+    /// redacted from `Debug` and `Display` output.
+    pub fn is_secret(&self) -> bool {
+        self.header_type == MessageType::PGPPrivateKeyBlock
+    }
+
+    /// Decode the base64 body into the octets it carries.
+    pub fn decode_body(&self) -> Option<Vec<u8>> {
+        base64::decode(&self.body)
+    }
+
+    /// Decode the base64 body into a buffer that is wiped when dropped. Use
+    /// this over `decode_body` for private key blocks.
+    pub fn decode_secret_body(&self) -> Option<SecretBytes> {
+        // The buffer is allocated up front so decoding never reallocates it.
+        let mut data = Vec::with_capacity(self.body.len() / 4 * 3);
+        let decoded = base64::decode_into(&self.body, &mut data);
+        let secret = SecretBytes::new(data);
+
+        decoded.map(|_| secret)
+    }
+
+    /// Decode the armor checksum into its CRC-24 value.
+    pub fn decode_checksum(&self) -> Option<crc24::Crc24> {
+        decode_checksum(&self.checksum)
+    }
+
+    /// Tests whether the armor checksum matches the CRC-24 of the decoded body.
+    pub fn has_valid_checksum(&self) -> bool {
+        match (self.decode_secret_body(), self.decode_checksum()) {
+            (Some(body), Some(checksum)) => crc24::crc_octets(&body) == checksum,
+            _ => false
+        }
+    }
+}
+
+impl fmt::Debug for ArmorMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("ArmorMessage");
+        debug.field("header_type", &self.header_type)
+             .field("header_block", &self.header_block);
+        if self.is_secret() {
+            debug.field("body", &format_args!("[REDACTED; {}]", self.body.len()))
+                 .field("checksum", &format_args!("[REDACTED]"));
+        } else {
+            debug.field("body", &self.body)
+                 .field("checksum", &self.checksum);
+        }
+
+        debug.finish()
+    }
+}
+
+// The armored form of the message, except that the body and checksum of a
+// private key block are replaced by a placeholder line.
+impl fmt::Display for ArmorMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_secret() {
+            write!(f, "{}", encode_redacted(self))
+        } else {
+            write!(f, "{}", encode(self))
+        }
+    }
+}
+
+// The base64 body and checksum of a private key block carry the secret key
+// material and a CRC of it.
+impl Drop for ArmorMessage {
+    fn drop(&mut self) {
+        zeroize::zeroize_string(&mut self.body);
+        zeroize::zeroize_string(&mut self.checksum);
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -85,6 +212,7 @@ pub enum ParseError {
     CorruptBody,
     EndOfFile,
     ParseError,
+    LimitExceeded(Limit),
 }
 
 pub type ParseResult<T> = Result<T, ParseError>;
@@ -110,7 +238,8 @@ impl fmt::Display for ParseError {
             ParseError::InvalidHeaderLine => write!(f, "Invalid header line."),
             ParseError::CorruptBody => write!(f, "Corrupt Base64 data."),
             ParseError::EndOfFile => write!(f, "Reached end of armored data."),
-            ParseError::ParseError => write!(f, "Parser error.")
+            ParseError::ParseError => write!(f, "Parser error."),
+            ParseError::LimitExceeded(limit) => write!(f, "Exceeded the {} limit.", limit)
         }
     }
 }
@@ -122,44 +251,144 @@ impl error::Error for ParseError {
             ParseError::InvalidHeaderLine => "A header line contains invalid data.",
             ParseError::CorruptBody => "The Base 64 payload of the armor message was corrupted.",
             ParseError::EndOfFile => "There is no more data available.",
-            ParseError::ParseError => "A general parsing error."
+            ParseError::ParseError => "A general parsing error.",
+            ParseError::LimitExceeded(_) => "The armor exceeds a resource limit of the parser."
         }
     }
 }
 
-pub struct Parser<S> where S: Iterator<Item=char> {
-    input:  Peekable<Lexer<S>>,
+/// Decode the base64 characters of an armor checksum into its CRC-24 value.
+pub fn decode_checksum(checksum: &str) -> Option<crc24::Crc24> {
+    match base64::decode(checksum) {
+        Some(ref octets) if octets.len() == 3 => {
+            Some(octets.iter().fold(0, |crc, &octet| (crc << 8) | octet as crc24::Crc24))
+        }
+        _ => None
+    }
+}
+
+// Grow a run of adjacent tokens by the next token.
+fn extend_span(span: Option<Span>, next: Span) -> Span {
+    match span {
+        Some(span) => Span::new(span.start, next.end),
+        None => next
+    }
+}
+
+// The length of the base64 lines at the start of the bytes, up to the line of
+// the armor checksum. Every line holds from 1 to 76 base64 characters and ends
+// in a newline, and padding may only end a line.
+fn body_length(bytes: &[u8]) -> Option<usize> {
+    let mut position = 0;
+    loop {
+        let line = &bytes[position..];
+        let data = line.iter().take(BASE64_LINE_LENGTH + 1).take_while(|&&byte| base64::is_base64_byte(byte)).count();
+        let padding = line[data..].iter().take(BASE64_LINE_LENGTH + 1).take_while(|&&byte| byte == b'=').count();
+        let length = data + padding;
+        if length == 0 || length > BASE64_LINE_LENGTH || line.get(length) != Some(&b'\n') {
+            return None;
+        }
+
+        position += length + 1;
+        match bytes.get(position) {
+            Some(&b'=') => return Some(position),
+            Some(_) => continue,
+            None => return None
+        }
+    }
+}
+
+// The number of base64 characters in body lines, without their newlines.
+fn base64_length(lines: &str) -> usize {
+    lines.len() - lines.bytes().filter(|&byte| byte == b'\n').count()
+}
+
+// Finds the line numbers of offsets into a text, moving forward through it.
+struct LineCounter<'a> {
+    text: &'a str,
+    offset: usize,
+    line: usize
+}
+
+impl<'a> LineCounter<'a> {
+    fn new(text: &'a str) -> LineCounter<'a> {
+        LineCounter {
+            text,
+            offset: 0,
+            line: 1
+        }
+    }
+
+    fn location(&mut self, offset: usize) -> Location {
+        self.line += self.text.as_bytes()[self.offset..offset].iter().filter(|&&byte| byte == b'\n').count();
+        self.offset = offset;
+
+        Location { line: self.line, offset }
+    }
+}
+
+/// A recursive descent parser for ascii armor. The parser works on the spans
+/// of the lexer tokens and only copies text into the message it returns.
+pub struct Parser<'a> {
+    input:     Lexer<'a>,
+    text:      &'a str,
     lookahead: VecDeque<Token>,
     markers: Vec<usize>,
-    offset: usize
+    offset: usize,
+    limits: Limits,
+    threads: usize,
+    // Running out of lookahead looks like the end of the input to the rules
+    // that peek, so the error is kept and reported in its place.
+    limit_error: Option<ParseError>
 }
 
-impl<S> Parser<S> where S: Iterator<Item=char> {
-    pub fn new(input: Lexer<S>) -> Parser<S> {
+impl<'a> Parser<'a> {
+    pub fn new(input: Lexer<'a>) -> Parser<'a> {
         Parser {
-            input:     input.peekable(),
+            text:      input.input(),
+            input,
             lookahead: VecDeque::with_capacity(20),
             markers:   Vec::new(),
-            offset:    0
+            offset:    0,
+            limits:    Limits::default(),
+            threads:   1,
+            limit_error: None
         }
     }
 
+    /// The resource limits to parse with. Defaults to `Limits::default()`.
+    pub fn limits(mut self, limits: Limits) -> Parser<'a> {
+        self.limits = limits;
+        self
+    }
+
+    /// The most threads `parse_decoded` decodes the body on. A body is split
+    /// on line boundaries into parts of at least a megabyte, which are decoded
+    /// in parallel. Defaults to 1, which decodes on the calling thread.
+    pub fn threads(mut self, threads: usize) -> Parser<'a> {
+        self.threads = threads;
+        self
+    }
+
     fn peek_token(&mut self) -> Option<Token> {
         if self.lookahead.is_empty() {
             self.offset = 0;
             let next_token = self.input.next();
-            if next_token.is_some() {
-                self.lookahead.push_back(next_token.clone().unwrap());
-                Some(next_token.unwrap())
-            } else {
-                None
+            if let Some(token) = next_token {
+                self.lookahead.push_back(token);
             }
+            next_token
         } else {
             self.sync();
-            Some(self.lookahead[self.offset].clone())
+            self.lookahead.get(self.offset).cloned()
         }
     }
 
+    // The input text covered by a run of tokens.
+    fn span_text(&self, span: Span) -> &'a str {
+        &self.text[span.start..span.end]
+    }
+
     fn peek_token_or_eof<F,T>(&mut self, f: F) -> ParseResult<T>
         where F: Fn(&mut Self, Token) -> ParseResult<T>
     {
@@ -178,14 +407,18 @@ impl<S> Parser<S> where S: Iterator<Item=char> {
     }
 
     fn sync(&mut self) {
-        if self.offset > self.lookahead.len()-1 {
-            let n = self.offset - (self.lookahead.len()-1);
+        if self.offset >= self.lookahead.len() {
+            let n = self.offset + 1 - self.lookahead.len();
             self.fill(n);
         }
     }
 
     fn fill(&mut self, amount: usize) {
         for _ in 0..amount {
+            if let Err(err) = self.limits.check(Limit::Lookahead, self.lookahead.len() + 1) {
+                self.limit_error = Some(err);
+                break;
+            }
             match self.input.next() {
                 Some(token) => {
                     self.lookahead.push_back(token);
@@ -233,20 +466,26 @@ impl<S> Parser<S> where S: Iterator<Item=char> {
 
     fn parse_number(&mut self) -> ParseResult<usize> {
         self.mark();
-        let mut result = String::new();
+        let mut digits: Option<Span> = None;
         while let Some(token) = self.peek_token() {
             match token.token_type() {
                 TokenType::Digit => {
                     self.read_token();
-                    result.push_str(token.as_str());
+                    digits = Some(extend_span(digits, token.span()));
                 }
                 _ => break
             }
         }
 
-        if !result.is_empty() {
-            let parse_result = result.parse::<usize>().unwrap();
-            Ok(parse_result)
+        if let Some(span) = digits {
+            // Too many digits for a usize is a malformed part number.
+            match self.span_text(span).parse::<usize>() {
+                Ok(number) => Ok(number),
+                Err(_) => {
+                    self.backtrack();
+                    Err(ParseError::CorruptHeader)
+                }
+            }
         } else if self.peek_token().is_none() {
             Err(ParseError::EndOfFile)
         } else {
@@ -389,30 +628,35 @@ impl<S> Parser<S> where S: Iterator<Item=char> {
         Ok(message_type)
     }
 
-    fn parse_header_line(&mut self) -> ParseResult<MessageType> {
+    /// Parse an armor header line, such as `-----BEGIN PGP MESSAGE-----`.
+    pub fn parse_header_line(&mut self) -> ParseResult<MessageType> {
         self.parse_header_tail_line(TokenType::Begin)
     }
 
-    fn parse_tail_line(&mut self) -> ParseResult<MessageType> {
+    /// Parse an armor tail line, such as `-----END PGP MESSAGE-----`.
+    pub fn parse_tail_line(&mut self) -> ParseResult<MessageType> {
         self.parse_header_tail_line(TokenType::End)
     }
 
     fn parse_header_text(&mut self) -> ParseResult<String> {
-        let mut result = String::new();
+        let mut text: Option<Span> = None;
         loop {
             match self.peek_token() {
                 Some(token) => {
                     match token.token_type() {
-                        TokenType::Version
-                            | TokenType::Comment
-                            | TokenType::MessageID
-                            | TokenType::Hash
-                            | TokenType::Charset
-                            | TokenType::BlankLine => {
-                                break;
+                        TokenType::BlankLine => {
+                            break;
+                        }
+                        TokenType::NewLine => {
+                            // The newline terminates the header line, it is not
+                            // part of the header text.
+                            self.read_token();
+                            break;
                         }
                         _ => {
-                            result.push_str(token.as_str());
+                            let span = extend_span(text, token.span());
+                            self.limits.check(Limit::HeaderLength, span.len())?;
+                            text = Some(span);
                             self.read_token();
                         }
                     }
@@ -421,7 +665,7 @@ impl<S> Parser<S> where S: Iterator<Item=char> {
             }
         }
 
-        Ok(result)
+        Ok(text.map_or_else(String::new, |span| String::from(self.span_text(span))))
     }
 
     fn skip_whitespace(&mut self) {
@@ -435,7 +679,9 @@ impl<S> Parser<S> where S: Iterator<Item=char> {
         }
     }
 
-    fn parse_headerkv(&mut self) -> ParseResult<(HeaderType, String)> {
+    /// Parse an armor header, such as `Version: 1.0`, up to the newline that
+    /// ends it (RFC 4880 Section 6.2).
+    pub fn parse_headerkv(&mut self) -> ParseResult<(HeaderType, String)> {
         let header_type = try!(self.peek_token_or_eof(|parser, token| {
             match token.token_type() {
                 tt @ TokenType::Version
@@ -469,6 +715,17 @@ impl<S> Parser<S> where S: Iterator<Item=char> {
 
     fn parse_header_block(&mut self) -> ParseResult<Vec<(HeaderType, String)>> {
         let mut result = Vec::new();
+        self.parse_header_block_with(&mut |key, val| result.push((key, val)))?;
+
+        Ok(result)
+    }
+
+    // Parse the armor headers up to the blank line that ends them, passing
+    // each one to a callback.
+    fn parse_header_block_with<F>(&mut self, on_header: &mut F) -> ParseResult<()>
+        where F: FnMut(HeaderType, String)
+    {
+        let mut count = 0;
         loop {
             match self.peek_token() {
                 Some(token) => {
@@ -478,8 +735,10 @@ impl<S> Parser<S> where S: Iterator<Item=char> {
                         | TokenType::MessageID
                         | TokenType::Hash
                         | TokenType::Charset => {
-                            try!(self.parse_headerkv()
-                                     .map(|(key, val)| { result.push((key, val)); }));
+                            count += 1;
+                            self.limits.check(Limit::Headers, count)?;
+                            let (key, val) = self.parse_headerkv()?;
+                            on_header(key, val);
                         }
                         TokenType::BlankLine => {
                             self.read_token();
@@ -497,12 +756,23 @@ impl<S> Parser<S> where S: Iterator<Item=char> {
         }
 
         self.consume();
-        Ok(result)
+        Ok(())
+    }
+
+    fn skip_header_line_end(&mut self) {
+        self.skip_whitespace();
+        // When armor headers are present the header line ends in a single newline
+        // rather than a blank line.
+        if let Some(token) = self.peek_token() {
+            if token.has_token_type(TokenType::NewLine) {
+                self.read_token();
+            }
+        }
     }
 
     fn parse_header(&mut self) -> ParseResult<Header> {
         let header_type: MessageType = try!(self.parse_header_line());
-        self.skip_whitespace();
+        self.skip_header_line_end();
         let header_block: Vec<(HeaderType, String)> = try!(self.parse_header_block());
 
         let header = Header {
@@ -518,9 +788,11 @@ impl<S> Parser<S> where S: Iterator<Item=char> {
         self.parse_tail_line()
     }
 
-    fn parse_body_line(&mut self) -> ParseResult<String> {
+    // Parse a line of the body into the span of its base64 data, padding
+    // included.
+    fn parse_body_line(&mut self) -> ParseResult<Option<Span>> {
         self.mark();
-        let mut line = String::new();
+        let mut line: Option<Span> = None;
         let mut i = 0;
         while i < BASE64_LINE_LENGTH {
             match self.peek_token() {
@@ -530,12 +802,13 @@ impl<S> Parser<S> where S: Iterator<Item=char> {
                             break;
                         }
                         TokenType::Pad => {
-                            // Parse out the padding to newline
+                            // Parse out the padding to newline. The padding is kept in
+                            // the line so that the body remains valid base64 data.
                             match self.parse_padding() {
                                 Ok(amount) => {
-                                    // We can consume the padding.
-                                    if i + amount < BASE64_LINE_LENGTH {
-                                        self.read_tokens(amount);
+                                    if i + amount <= BASE64_LINE_LENGTH {
+                                        let padding = Span::new(token.span().start, token.span().start + amount);
+                                        line = Some(extend_span(line, padding));
                                         break;
                                     } else {
                                         return self.backtrack_with_error(Err(ParseError::CorruptBody));
@@ -545,10 +818,10 @@ impl<S> Parser<S> where S: Iterator<Item=char> {
                             }
                         }
                         _ => {
-                            let slice = token.as_str();
-                            if i + slice.len() < BASE64_LINE_LENGTH {
-                                line.push_str(slice);
-                                i += 1;
+                            let slice = token.text(self.text);
+                            if base64::is_base64_str(slice) && (i + slice.len() <= BASE64_LINE_LENGTH) {
+                                line = Some(extend_span(line, token.span()));
+                                i += slice.len();
                                 self.read_token();
                             } else {
                                 return self.backtrack_with_error(Err(ParseError::CorruptBody));
@@ -600,19 +873,104 @@ impl<S> Parser<S> where S: Iterator<Item=char> {
         Ok(count)
     }
 
-    fn parse_body(&mut self) -> ParseResult<String> {
+    // Also returns the span of the body lines when the fast path found them.
+    fn parse_body(&mut self) -> ParseResult<(String, Option<Span>)> {
+        match self.body_span() {
+            Some(span) => Ok((self.parse_body_bytes(span)?, Some(span))),
+            None => Ok((self.parse_body_tokens()?, None))
+        }
+    }
+
+    fn parse_body_bytes(&mut self, span: Span) -> ParseResult<String> {
+        let text = self.span_text(span);
+        let length = base64_length(text);
+        self.limits.check(Limit::BodyLength, length)?;
+
+        // The capacity covers the whole body, so the buffer is never reallocated
+        // with body data in it.
+        let mut body = String::with_capacity(length);
+        for line in text.split_terminator('\n') {
+            body.push_str(line);
+        }
+
+        Ok(body)
+    }
+
+    // The fast path for the body, which is almost all of a block. The base64
+    // lines are checked straight from the input bytes with a lookup table
+    // instead of being lexed one character at a time. Returns the span of the
+    // body lines, and the parser continues after them. Anything but a well
+    // formed body is left to the token parser, which reports the error.
+    fn body_span(&mut self) -> Option<Span> {
+        let start = self.lookahead.front().map_or(self.input.position(), |token| token.span().start);
+        let end = start + body_length(&self.text.as_bytes()[start..])?;
+
+        self.lookahead.clear();
+        self.markers.clear();
+        self.offset = 0;
+        self.input.seek(end);
+
+        Some(Span::new(start, end))
+    }
+
+    // Parse the body, passing each base64 line and its offset in the input to
+    // a callback.
+    fn parse_body_with<F>(&mut self, on_line: &mut F) -> ParseResult<()>
+        where F: FnMut(&'a str, usize)
+    {
+        match self.body_span() {
+            Some(span) => {
+                let text = self.span_text(span);
+                let length = base64_length(text);
+                self.limits.check(Limit::BodyLength, length)?;
+
+                let mut offset = span.start;
+                for line in text.split_terminator('\n') {
+                    on_line(line, offset);
+                    offset += line.len() + 1;
+                }
+            }
+            None => {
+                self.mark();
+                self.parse_body_lines(on_line)?;
+                self.consume();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_body_tokens(&mut self) -> ParseResult<String> {
         self.mark();
         let mut string = String::new();
+        if let Err(e) = self.parse_body_lines(&mut |line, _| zeroize::push_str(&mut string, line)) {
+            // The partial body may hold secret key material.
+            zeroize::zeroize_string(&mut string);
+            return Err(e);
+        }
+
+        self.consume();
+        Ok(string)
+    }
+
+    fn parse_body_lines<F>(&mut self, on_line: &mut F) -> ParseResult<()>
+        where F: FnMut(&'a str, usize)
+    {
+        let mut length = 0;
         loop {
             match self.parse_body_line() {
-                Ok(other_string) => {
-                    string.push_str(other_string.as_str());
+                Ok(line) => {
+                    if let Some(span) = line {
+                        length += span.len();
+                        self.limits.check(Limit::BodyLength, length)?;
+                        on_line(self.span_text(span), span.start);
+                    }
                     match self.peek_token() {
                         Some(token) => {
                             match token.token_type() {
                                 TokenType::Pad => {
                                     // We are at the end of the base 64 data.
-                                    break;
+                                    return Ok(());
                                 }
                                 TokenType::FiveDashes => {
                                     // The padding line is missing.
@@ -629,9 +987,6 @@ impl<S> Parser<S> where S: Iterator<Item=char> {
                 Err(e) => return Err(e)
             }
         }
-
-        self.consume();
-        Ok(string)
     }
 
     fn parse_checksum(&mut self) -> ParseResult<String> {
@@ -654,9 +1009,11 @@ impl<S> Parser<S> where S: Iterator<Item=char> {
         while i < 4 {
             match self.peek_token() {
                 Some(token) => {
-                    if base64::is_base64(token.as_bytes()) && (i + token.as_bytes().len() < 4) {
-                        checksum.push_str(token.as_str());
-                        i += token.as_bytes().len();
+                    let slice = token.text(self.text);
+                    if base64::is_base64_str(slice) && (i + slice.len() <= 4) {
+                        checksum.push_str(slice);
+                        i += slice.len();
+                        self.read_token();
                     } else {
                         return Err(ParseError::CorruptBody)
                     }
@@ -681,14 +1038,70 @@ impl<S> Parser<S> where S: Iterator<Item=char> {
         Ok(checksum)
     }
 
+    /// Parse the armor, reporting its structure to a visitor instead of
+    /// building a message. Body lines are passed as slices of the input, so
+    /// nothing the size of the body is allocated.
+    pub fn visit<V: Visitor>(&mut self, visitor: &mut V) -> ParseResult<()> {
+        let result = self.visit_message(visitor);
+        self.limit_result(result)
+    }
+
+    fn visit_message<V: Visitor>(&mut self, visitor: &mut V) -> ParseResult<()> {
+        let header_type = self.parse_header_line()?;
+        visitor.on_begin(&header_type);
+        self.skip_header_line_end();
+        self.parse_header_block_with(&mut |key, val| visitor.on_header(&key, &val))?;
+
+        let mut lines = LineCounter::new(self.text);
+        self.parse_body_with(&mut |line, offset| visitor.on_body_line(line, lines.location(offset)))?;
+
+        let checksum = self.parse_checksum()?;
+        visitor.on_checksum(&checksum);
+        let tail = self.parse_tail()?;
+        if tail != header_type {
+            return Err(ParseError::ParseError);
+        }
+        visitor.on_end(&tail);
+
+        Ok(())
+    }
+
     pub fn parse(&mut self) -> ParseResult<ArmorMessage> {
-        let header   = try!(self.parse_header());
-        let body     = try!(self.parse_body());
-        let checksum = try!(self.parse_checksum());
-        let tail     = try!(self.parse_tail());
+        let result = self.parse_message().map(|(message, _)| message);
+        self.limit_result(result)
+    }
+
+    /// Parse the armor and decode its body, on as many threads as the parser
+    /// is given. The message is the one `parse` returns, and the decoded body
+    /// holds the octets `ArmorMessage::decode_body` gives. A body that parses
+    /// but does not decode is a `CorruptBody` error.
+    pub fn parse_decoded(&mut self) -> ParseResult<(ArmorMessage, DecodedBody)> {
+        let result = self.parse_message().and_then(|(message, span)| {
+            // The body lines found by the fast path are decoded in place, so
+            // the parts can be cut on their line breaks.
+            let body = span.map_or(message.body(), |span| self.span_text(span));
+            let decoded = BodyDecoder::new(self.threads).decode(body)?;
+            Ok((message, decoded))
+        });
+        self.limit_result(result)
+    }
+
+    // Report running out of lookahead over whatever the rules made of it.
+    fn limit_result<T>(&self, result: ParseResult<T>) -> ParseResult<T> {
+        match self.limit_error {
+            Some(err) => Err(err),
+            None => result
+        }
+    }
+
+    fn parse_message(&mut self) -> ParseResult<(ArmorMessage, Option<Span>)> {
+        let header       = try!(self.parse_header());
+        let (body, span) = try!(self.parse_body());
+        let checksum     = try!(self.parse_checksum());
+        let tail         = try!(self.parse_tail());
 
         if header.header_type == tail {
-            Ok(ArmorMessage::new(header.header_type, header.header_block, body, checksum))
+            Ok((ArmorMessage::new(header.header_type, header.header_block, body, checksum), span))
         } else {
             Err(ParseError::ParseError)
         }
@@ -699,7 +1112,9 @@ impl<S> Parser<S> where S: Iterator<Item=char> {
 #[cfg(test)]
 mod tests {
     use lexer::Lexer;
-    use super::{Parser, HeaderType, MessageType, Header};
+    use limits::{Limit, Limits};
+    use visitor::Visitor;
+    use super::{Parser, ArmorMessage, HeaderType, MessageType, Header, ParseError};
 
 
     struct HeaderLineTest {
@@ -717,7 +1132,7 @@ mod tests {
     }
 
     fn run_header_line_test(test: &HeaderLineTest) {
-        let lexer  = Lexer::new(test.header_line.chars());
+        let lexer  = Lexer::new(&test.header_line);
         let mut parser = Parser::new(lexer);
         let result = parser.parse_header_line();
         assert!(result.is_ok());
@@ -725,7 +1140,7 @@ mod tests {
     }
 
     fn run_tail_line_test(test: &HeaderLineTest) {
-        let lexer  = Lexer::new(test.header_line.chars());
+        let lexer  = Lexer::new(&test.header_line);
         let mut parser = Parser::new(lexer);
         let result = parser.parse_tail_line();
         assert!(result.is_ok());
@@ -850,7 +1265,7 @@ mod tests {
 
     fn run_header_tests(tests: &HeaderTest) {
         for test_case in tests.data.iter() {
-            let lexer = Lexer::new(test_case.text.chars());
+            let lexer = Lexer::new(&test_case.text);
             let mut parser = Parser::new(lexer);
             let result = parser.parse_header().unwrap();
             assert_eq!(result.header_type, test_case.header.header_type);
@@ -862,4 +1277,183 @@ mod tests {
         run_header_tests(&header_test_cases());
     }
 
+    #[test]
+    fn test_parse() {
+        let text = "-----BEGIN PGP MESSAGE-----\n\
+                    Version: OpenPrivacy 0.99\n\
+                    Comment: Version 2 of the message\n\
+                    \n\
+                    yDgBO22WxBHv7O8X7O/jygAEzol56iUKiXmV+XmpCtmpqQUKiQrFqclFqUDBovzS\n\
+                    vBSFjNSiVHsuAA==\n\
+                    =njUN\n\
+                    -----END PGP MESSAGE-----";
+        let lexer = Lexer::new(text);
+        let mut parser = Parser::new(lexer);
+        let message = parser.parse().unwrap();
+
+        assert_eq!(message.header_type(), &MessageType::PGPMessage);
+        assert_eq!(message.header_block(), &[
+            (HeaderType::Version, String::from("OpenPrivacy 0.99")),
+            (HeaderType::Comment, String::from("Version 2 of the message"))
+        ][..]);
+        assert_eq!(message.body(), "yDgBO22WxBHv7O8X7O/jygAEzol56iUKiXmV+XmpCtmpqQUKiQrFqclFqUDBovzSvBSFjNSiVHsuAA==");
+        assert_eq!(message.checksum(), "njUN");
+        assert!(message.has_valid_checksum());
+    }
+
+    #[test]
+    fn test_body_length() {
+        assert_eq!(super::body_length(b"vBSFjNSiVHsuAA==\n=njUN\n"), Some(17));
+        assert_eq!(super::body_length(b"Zm9v\nYg==\n=njUN"), Some(10));
+        assert_eq!(super::body_length(b"Zm9v\n\nYg==\n=njUN"), None);
+        assert_eq!(super::body_length(b"Zm=9v\n=njUN"), None);
+        assert_eq!(super::body_length(b"Zm9v\r\n=njUN"), None);
+        assert_eq!(super::body_length(b"Zm9v\n-----END PGP MESSAGE-----"), None);
+        assert_eq!(super::body_length("A".repeat(77).as_bytes()), None);
+    }
+
+    // The body fast path must accept exactly what the token parser accepts.
+    #[test]
+    fn test_body_fast_path() {
+        let texts = [
+            include_str!("../sample/message.asc"),
+            include_str!("../sample/pub.asc"),
+            include_str!("../sample/priv.asc"),
+            "-----BEGIN PGP MESSAGE-----\n\n\nvBSFjNSiVHsuAA==\n=njUN\n-----END PGP MESSAGE-----\n",
+            "-----BEGIN PGP MESSAGE-----\n\nvBSF=jNSiVHsuAA==\n=njUN\n-----END PGP MESSAGE-----\n",
+            "-----BEGIN PGP MESSAGE-----\n\nvBSFjNSiVHsuAA==\n-----END PGP MESSAGE-----\n",
+            "-----BEGIN PGP MESSAGE-----\n\nvBSFjNSiVHsuAA==\r\n=njUN\n-----END PGP MESSAGE-----\n"
+        ];
+
+        for text in texts.iter() {
+            let mut fast = Parser::new(Lexer::new(text));
+            let mut tokens = Parser::new(Lexer::new(text));
+            assert_eq!(fast.parse_header(), tokens.parse_header());
+            assert_eq!(fast.parse_body().map(|(body, _)| body), tokens.parse_body_tokens());
+            assert_eq!(fast.parse_checksum(), tokens.parse_checksum());
+            assert_eq!(fast.parse_tail(), tokens.parse_tail());
+        }
+    }
+
+    #[test]
+    fn test_checksum() {
+        for text in [include_str!("../sample/message.asc"),
+                     include_str!("../sample/pub.asc"),
+                     include_str!("../sample/priv.asc")].iter() {
+            let message = Parser::new(Lexer::new(text)).parse().unwrap();
+            assert!(message.has_valid_checksum());
+
+            let corrupt = ArmorMessage::new(message.header_type().clone(),
+                                            message.header_block().to_vec(),
+                                            String::from(message.body()),
+                                            String::from("AAAA"));
+            assert!(!corrupt.has_valid_checksum());
+        }
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        let text = include_str!("../sample/message.asc");
+        // An empty line before the body takes the token parser's path through it.
+        let spaced = text.replacen("\n\n", "\n\n\n", 1);
+        let tests = [
+            (Limits::new().max_headers(0), Limit::Headers),
+            (Limits::new().max_header_length(15), Limit::HeaderLength),
+            (Limits::new().max_body_length(79), Limit::BodyLength),
+            (Limits::new().max_lookahead(4), Limit::Lookahead)
+        ];
+
+        for &(limits, limit) in tests.iter() {
+            for text in [text, &spaced[..]].iter() {
+                let result = Parser::new(Lexer::new(text)).limits(limits).parse();
+                assert_eq!(result.err(), Some(ParseError::LimitExceeded(limit)));
+            }
+        }
+        let limits = Limits::new().max_headers(1).max_header_length(16).max_body_length(80).max_lookahead(80);
+        for text in [text, &spaced[..]].iter() {
+            assert!(Parser::new(Lexer::new(text)).limits(limits).parse().is_ok());
+        }
+
+        let digits = format!("-----BEGIN PGP MESSAGE, PART {}-----\n", "1".repeat(10000));
+        let result = Parser::new(Lexer::new(&digits)).parse();
+        assert_eq!(result.err(), Some(ParseError::LimitExceeded(Limit::Lookahead)));
+    }
+
+    #[test]
+    fn test_parse_decoded() {
+        let spaced = include_str!("../sample/pub.asc").replacen("\n\n", "\n\n\n", 1);
+        for text in [include_str!("../sample/message.asc"), include_str!("../sample/priv.asc"), &spaced[..]].iter() {
+            let message = Parser::new(Lexer::new(text)).parse().unwrap();
+            let (decoded_message, decoded) = Parser::new(Lexer::new(text)).threads(4).parse_decoded().unwrap();
+
+            assert_eq!(decoded_message, message);
+            assert_eq!(decoded.octets(), &message.decode_body().unwrap()[..]);
+            assert_eq!(Some(decoded.checksum()), message.decode_checksum());
+        }
+
+        // A body that parses but does not decode.
+        let text = "-----BEGIN PGP MESSAGE-----\n\nZg==\nZm9v\n=njUN\n-----END PGP MESSAGE-----\n";
+        assert!(Parser::new(Lexer::new(text)).parse().is_ok());
+        assert_eq!(Parser::new(Lexer::new(text)).threads(4).parse_decoded().err(), Some(ParseError::CorruptBody));
+    }
+
+    // A body of two megabytes is split between two threads, with the same
+    // octets and errors as on one thread.
+    #[test]
+    fn test_parse_decoded_threads() {
+        let octets: Vec<u8> = (0..3usize << 19).map(|i| (i * 31 + i / 7) as u8).collect();
+        let mut armor = Vec::new();
+        let mut encoder = ::encoder::ArmorEncoder::new(&MessageType::PGPMessage, &[]);
+        encoder.update(&octets, &mut armor);
+        encoder.finish(&mut armor);
+        let text = String::from_utf8(armor).unwrap();
+
+        for &threads in [1, 2].iter() {
+            let (message, decoded) = Parser::new(Lexer::new(&text)).threads(threads).parse_decoded().unwrap();
+            assert_eq!(decoded.octets(), &octets[..]);
+            assert_eq!(Some(decoded.checksum()), message.decode_checksum());
+        }
+
+        // Padding partway through the second part of the body.
+        let line = text.len() * 3 / 4;
+        let line = line + text[line..].find('\n').unwrap() + 1;
+        let damaged = format!("{}Zg==\n{}", &text[..line], &text[line..]);
+        assert!(Parser::new(Lexer::new(&damaged)).parse().unwrap().decode_body().is_none());
+        for &threads in [1, 2].iter() {
+            let result = Parser::new(Lexer::new(&damaged)).threads(threads).parse_decoded();
+            assert_eq!(result.err(), Some(ParseError::CorruptBody));
+        }
+    }
+
+    #[test]
+    fn test_parse_part_number_overflow() {
+        let text = format!("-----BEGIN PGP MESSAGE, PART {}-----\n", "9".repeat(40));
+        assert_eq!(Parser::new(Lexer::new(&text)).parse_header_line(), Err(ParseError::CorruptHeader));
+        let text = format!("-----BEGIN PGP MESSAGE, PART 1/{}-----\n", "9".repeat(40));
+        assert_eq!(Parser::new(Lexer::new(&text)).parse_header_line(), Err(ParseError::CorruptHeader));
+    }
+
+    // Damage the samples one character at a time. Every parser must return
+    // an error or a message, never panic.
+    #[test]
+    fn test_parse_damaged_input() {
+        struct Ignore;
+        impl Visitor for Ignore {}
+
+        let replacements = ["", "\n", "\r\n", "=", "-", " ", "9", "/", "A", ": ", "\u{e9}", "-----"];
+        let text = include_str!("../sample/message.asc");
+        for position in 0..text.len() {
+            for replacement in replacements.iter() {
+                let damaged = format!("{}{}{}", &text[..position], replacement, &text[position + 1..]);
+                let _ = Parser::new(Lexer::new(&damaged)).parse();
+                let _ = Parser::new(Lexer::new(&damaged)).visit(&mut Ignore);
+                let _ = Parser::new(Lexer::new(&damaged)).parse_decoded();
+                let _ = ::validate::validate(damaged.as_bytes());
+                let mut parser = ::push::PushParser::new();
+                let _ = parser.feed(damaged.as_bytes(), &mut Vec::new());
+                let _ = parser.finish(&mut Vec::new());
+            }
+            let _ = Parser::new(Lexer::new(&text[..position])).parse();
+        }
+    }
 }
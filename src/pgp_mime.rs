@@ -0,0 +1,387 @@
+use std::error;
+use std::fmt;
+use encoder;
+use mime::{MimePart, ContentType};
+use parser::{ArmorMessage, MessageType};
+use scanner;
+
+
+const PGP_ENCRYPTED: &str = "application/pgp-encrypted";
+const PGP_SIGNATURE: &str = "application/pgp-signature";
+const OCTET_STREAM: &str = "application/octet-stream";
+
+// The characters a multipart boundary may hold besides letters and digits
+// (RFC 2046 Section 5.1.1), and its longest length.
+const BOUNDARY_CHARACTERS: &str = "'()+_,-./:=? ";
+const MAX_BOUNDARY_LENGTH: usize = 70;
+// The characters a MIME token may not hold besides spaces and controls
+// (RFC 2045 Section 5.1).
+const TSPECIALS: &str = "()<>@,;:\\\"/[]?=";
+
+
+/// The armored OpenPGP data carried by a PGP/MIME message, as described in
+/// RFC3156.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PgpMime<'a> {
+    /// A `multipart/encrypted` entity and the `PGP MESSAGE` in its second part.
+    Encrypted(ArmorMessage),
+    /// A `multipart/signed` entity. The content is the first body part exactly
+    /// as it appears in the message, headers included, since that is what the
+    /// signature covers.
+    Signed {
+        content: &'a str,
+        micalg: Option<String>,
+        signature: ArmorMessage
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PgpMimeError {
+    NotPgpMime,
+    MissingBoundary,
+    MissingPart,
+    InvalidControlPart,
+    CorruptTransferEncoding,
+    MissingArmor,
+    InvalidMessageType,
+    BoundaryInContent,
+    InvalidBoundary,
+    InvalidMicalg
+}
+
+impl fmt::Display for PgpMimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PgpMimeError::NotPgpMime => write!(f, "Not a PGP/MIME message."),
+            PgpMimeError::MissingBoundary => write!(f, "Missing multipart boundary."),
+            PgpMimeError::MissingPart => write!(f, "Missing body part."),
+            PgpMimeError::InvalidControlPart => write!(f, "Invalid control part."),
+            PgpMimeError::CorruptTransferEncoding => write!(f, "Corrupt transfer encoding."),
+            PgpMimeError::MissingArmor => write!(f, "Missing armored data."),
+            PgpMimeError::InvalidMessageType => write!(f, "Invalid armor message type."),
+            PgpMimeError::BoundaryInContent => write!(f, "Boundary occurs in the content."),
+            PgpMimeError::InvalidBoundary => write!(f, "Invalid multipart boundary."),
+            PgpMimeError::InvalidMicalg => write!(f, "Invalid micalg parameter.")
+        }
+    }
+}
+
+impl error::Error for PgpMimeError {
+    fn description(&self) -> &str {
+        match *self {
+            PgpMimeError::NotPgpMime => "The message contains no multipart/encrypted or multipart/signed entity.",
+            PgpMimeError::MissingBoundary => "The multipart entity has no boundary or the boundary never occurs.",
+            PgpMimeError::MissingPart => "The multipart entity does not have the two required body parts.",
+            PgpMimeError::InvalidControlPart => "The application/pgp-encrypted part is not a version 1 control part.",
+            PgpMimeError::CorruptTransferEncoding => "The transfer encoding of a body part could not be undone.",
+            PgpMimeError::MissingArmor => "The body part does not contain armored data of the expected type.",
+            PgpMimeError::InvalidMessageType => "The armor message has the wrong type for this PGP/MIME entity.",
+            PgpMimeError::BoundaryInContent => "The boundary occurs inside the content it delimits.",
+            PgpMimeError::InvalidBoundary => "The boundary is empty, too long, or holds characters RFC 2046 does not allow.",
+            PgpMimeError::InvalidMicalg => "The micalg parameter is not a pgp- hash symbol token as RFC 3156 requires."
+        }
+    }
+}
+
+pub type PgpMimeResult<T> = Result<T, PgpMimeError>;
+
+/// Find the first `multipart/encrypted` or `multipart/signed` entity in an
+/// RFC5322 message, searching nested multipart entities, and extract its
+/// armored data.
+pub fn extract(message: &str) -> PgpMimeResult<PgpMime<'_>> {
+    find_pgp_mime(&MimePart::parse(message))
+}
+
+fn find_pgp_mime<'a>(part: &MimePart<'a>) -> PgpMimeResult<PgpMime<'a>> {
+    let content_type = part.content_type();
+    match content_type.mime_type() {
+        "multipart/encrypted" if has_protocol(&content_type, PGP_ENCRYPTED) => {
+            extract_encrypted(part)
+        }
+        "multipart/signed" if has_protocol(&content_type, PGP_SIGNATURE) => {
+            extract_signed(part, &content_type)
+        }
+        _ if content_type.is_multipart() => {
+            for body in part.multipart_bodies().unwrap_or_default() {
+                match find_pgp_mime(&MimePart::parse(body)) {
+                    Err(PgpMimeError::NotPgpMime) => continue,
+                    result => return result
+                }
+            }
+            Err(PgpMimeError::NotPgpMime)
+        }
+        _ => Err(PgpMimeError::NotPgpMime)
+    }
+}
+
+fn has_protocol(content_type: &ContentType, protocol: &str) -> bool {
+    content_type.parameter("protocol")
+                .map_or(false, |value| value.eq_ignore_ascii_case(protocol))
+}
+
+fn body_parts<'a>(part: &MimePart<'a>) -> PgpMimeResult<(MimePart<'a>, MimePart<'a>)> {
+    let bodies = part.multipart_bodies().ok_or(PgpMimeError::MissingBoundary)?;
+    if bodies.len() < 2 {
+        return Err(PgpMimeError::MissingPart);
+    }
+
+    Ok((MimePart::parse(bodies[0]), MimePart::parse(bodies[1])))
+}
+
+fn find_armor(part: &MimePart, mime_type: &str, message_type: MessageType) -> PgpMimeResult<ArmorMessage> {
+    if part.content_type().mime_type() != mime_type {
+        return Err(PgpMimeError::MissingPart);
+    }
+
+    let text = part.decoded_body().ok_or(PgpMimeError::CorruptTransferEncoding)?;
+    scanner::scan(&text).map(|block| block.into_message())
+                        .find(|message| *message.header_type() == message_type)
+                        .ok_or(PgpMimeError::MissingArmor)
+}
+
+fn extract_encrypted<'a>(part: &MimePart<'a>) -> PgpMimeResult<PgpMime<'a>> {
+    let (control, data) = body_parts(part)?;
+
+    let is_version_one = control.decoded_body()
+                                .map_or(false, |body| body.lines().any(|line| line.trim() == "Version: 1"));
+    if control.content_type().mime_type() != PGP_ENCRYPTED || !is_version_one {
+        return Err(PgpMimeError::InvalidControlPart);
+    }
+
+    find_armor(&data, OCTET_STREAM, MessageType::PGPMessage).map(PgpMime::Encrypted)
+}
+
+fn extract_signed<'a>(part: &MimePart<'a>, content_type: &ContentType) -> PgpMimeResult<PgpMime<'a>> {
+    let bodies = part.multipart_bodies().ok_or(PgpMimeError::MissingBoundary)?;
+    let (_, signature_part) = body_parts(part)?;
+    let signature = find_armor(&signature_part, PGP_SIGNATURE, MessageType::PGPSignature)?;
+
+    Ok(PgpMime::Signed {
+        content: bodies[0],
+        micalg: content_type.parameter("micalg").map(String::from),
+        signature
+    })
+}
+
+// A boundary of 1 to 70 characters from the RFC 2046 set, not ending in a
+// space. Anything else could end the header it is written into.
+fn check_boundary(boundary: &str) -> PgpMimeResult<()> {
+    let valid = boundary.chars().all(|ch| ch.is_ascii_alphanumeric() || BOUNDARY_CHARACTERS.contains(ch))
+        && !boundary.is_empty()
+        && boundary.len() <= MAX_BOUNDARY_LENGTH
+        && !boundary.ends_with(' ');
+
+    if valid { Ok(()) } else { Err(PgpMimeError::InvalidBoundary) }
+}
+
+// A micalg value is `pgp-` and a hash symbol, written as a MIME token (RFC
+// 3156 Section 5).
+fn check_micalg(micalg: &str) -> PgpMimeResult<()> {
+    let is_token_char = |ch: char| ch.is_ascii() && !ch.is_ascii_control() && ch != ' ' && !TSPECIALS.contains(ch);
+    let valid = micalg.len() > 4
+        && micalg[..4].eq_ignore_ascii_case("pgp-")
+        && micalg.chars().all(is_token_char);
+
+    if valid { Ok(()) } else { Err(PgpMimeError::InvalidMicalg) }
+}
+
+// MIME requires CRLF line endings while the encoder terminates lines with a
+// single newline.
+fn encode_crlf(message: &ArmorMessage) -> String {
+    encoder::encode(message).replace('\n', "\r\n")
+}
+
+/// Build a `multipart/encrypted` entity, headers included, around a
+/// `PGP MESSAGE`.
+pub fn build_encrypted(message: &ArmorMessage, boundary: &str) -> PgpMimeResult<String> {
+    if *message.header_type() != MessageType::PGPMessage {
+        return Err(PgpMimeError::InvalidMessageType);
+    }
+    check_boundary(boundary)?;
+
+    let armor = encode_crlf(message);
+    if armor.contains(boundary) {
+        return Err(PgpMimeError::BoundaryInContent);
+    }
+
+    Ok(format!("Content-Type: multipart/encrypted; protocol=\"{protocol}\";\r\n \
+                boundary=\"{boundary}\"\r\n\
+                \r\n\
+                This is an OpenPGP/MIME encrypted message (RFC 4880 and 3156)\r\n\
+                --{boundary}\r\n\
+                Content-Type: {protocol}\r\n\
+                Content-Description: PGP/MIME version identification\r\n\
+                \r\n\
+                Version: 1\r\n\
+                \r\n\
+                --{boundary}\r\n\
+                Content-Type: {octet_stream}; name=\"encrypted.asc\"\r\n\
+                Content-Description: OpenPGP encrypted message\r\n\
+                Content-Disposition: inline; filename=\"encrypted.asc\"\r\n\
+                \r\n\
+                {armor}\r\n\
+                --{boundary}--\r\n",
+               protocol = PGP_ENCRYPTED,
+               octet_stream = OCTET_STREAM,
+               boundary = boundary,
+               armor = armor))
+}
+
+/// Build a `multipart/signed` entity, headers included, from the signed MIME
+/// entity and its detached `PGP SIGNATURE`. The content is inserted unchanged,
+/// so it must already be in the canonical form that was signed.
+pub fn build_signed(content: &str, signature: &ArmorMessage, micalg: &str, boundary: &str) -> PgpMimeResult<String> {
+    if *signature.header_type() != MessageType::PGPSignature {
+        return Err(PgpMimeError::InvalidMessageType);
+    }
+    check_micalg(micalg)?;
+    check_boundary(boundary)?;
+
+    let armor = encode_crlf(signature);
+    if content.contains(boundary) || armor.contains(boundary) {
+        return Err(PgpMimeError::BoundaryInContent);
+    }
+
+    Ok(format!("Content-Type: multipart/signed; micalg={micalg};\r\n \
+                protocol=\"{protocol}\"; boundary=\"{boundary}\"\r\n\
+                \r\n\
+                This is an OpenPGP/MIME signed message (RFC 4880 and 3156)\r\n\
+                --{boundary}\r\n\
+                {content}\r\n\
+                --{boundary}\r\n\
+                Content-Type: {protocol}; name=\"signature.asc\"\r\n\
+                Content-Description: OpenPGP digital signature\r\n\
+                Content-Disposition: attachment; filename=\"signature.asc\"\r\n\
+                \r\n\
+                {armor}\r\n\
+                --{boundary}--\r\n",
+               micalg = micalg,
+               protocol = PGP_SIGNATURE,
+               boundary = boundary,
+               content = content,
+               armor = armor))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use parser::{ArmorMessage, MessageType, HeaderType};
+    use super::{PgpMime, PgpMimeError};
+
+
+    fn pgp_message() -> ArmorMessage {
+        ArmorMessage::new(
+            MessageType::PGPMessage,
+            vec![(HeaderType::Version, String::from("OpenPrivacy 0.99"))],
+            String::from("yDgBO22WxBHv7O8X7O/jygAEzol56iUKiXmV+XmpCtmpqQUKiQrFqclFqUDBovzSvBSFjNSiVHsuAA=="),
+            String::from("njUN")
+        )
+    }
+
+    fn pgp_signature() -> ArmorMessage {
+        ArmorMessage::new(
+            MessageType::PGPSignature,
+            vec![],
+            String::from("vBSFjNSiVHsuAA=="),
+            String::from("njUN")
+        )
+    }
+
+    fn signed_content() -> &'static str {
+        "Content-Type: text/plain; charset=us-ascii\r\n\
+         \r\n\
+         Hello, Bob.\r\n"
+    }
+
+    fn nested_encrypted_message() -> String {
+        String::from(
+            "From: Alice <alice@example.org>\r\n\
+             To: Bob <bob@example.org>\r\n\
+             Subject: Secret\r\n\
+             MIME-Version: 1.0\r\n\
+             Content-Type: multipart/mixed; boundary=\"outer\"\r\n\
+             \r\n\
+             --outer\r\n\
+             Content-Type: text/plain\r\n\
+             \r\n\
+             See the attachment.\r\n\
+             --outer\r\n\
+             Content-Type: multipart/encrypted; boundary=inner;\r\n\
+             \tprotocol=\"application/pgp-encrypted\"\r\n\
+             \r\n\
+             --inner\r\n\
+             Content-Type: application/pgp-encrypted\r\n\
+             \r\n\
+             Version: 1\r\n\
+             --inner\r\n\
+             Content-Type: application/octet-stream\r\n\
+             Content-Transfer-Encoding: quoted-printable\r\n\
+             \r\n\
+             -----BEGIN PGP MESSAGE-----\r\n\
+             Version: OpenPrivacy 0.99\r\n\
+             \r\n\
+             yDgBO22WxBHv7O8X7O/jygAEzol56iUKiXmV+XmpCtmpqQUKiQrFqclFqUDBovzS\r\n\
+             vBSFjNSiVHsuAA=3D=3D\r\n\
+             =3DnjUN\r\n\
+             -----END PGP MESSAGE-----\r\n\
+             --inner--\r\n\
+             --outer--\r\n")
+    }
+
+    #[test]
+    fn test_encrypted_round_trip() {
+        let entity = super::build_encrypted(&pgp_message(), "boundary-1").unwrap();
+        assert_eq!(super::extract(&entity), Ok(PgpMime::Encrypted(pgp_message())));
+    }
+
+    #[test]
+    fn test_signed_round_trip() {
+        let entity = super::build_signed(signed_content(), &pgp_signature(), "pgp-sha256", "boundary-2").unwrap();
+        let expected = PgpMime::Signed {
+            content: signed_content(),
+            micalg: Some(String::from("pgp-sha256")),
+            signature: pgp_signature()
+        };
+
+        assert_eq!(super::extract(&entity), Ok(expected));
+    }
+
+    #[test]
+    fn test_extract_nested_encrypted_message() {
+        let message = nested_encrypted_message();
+        assert_eq!(super::extract(&message), Ok(PgpMime::Encrypted(pgp_message())));
+    }
+
+    #[test]
+    fn test_extract_errors() {
+        assert_eq!(super::extract("Content-Type: text/plain\r\n\r\nHello\r\n"), Err(PgpMimeError::NotPgpMime));
+
+        let message = nested_encrypted_message().replace("Version: 1\r\n", "Version: 2\r\n");
+        assert_eq!(super::extract(&message), Err(PgpMimeError::InvalidControlPart));
+
+        let message = nested_encrypted_message().replace("BEGIN PGP MESSAGE", "BEGIN PGP SIGNATURE");
+        assert_eq!(super::extract(&message), Err(PgpMimeError::MissingArmor));
+    }
+
+    #[test]
+    fn test_build_errors() {
+        assert_eq!(super::build_encrypted(&pgp_signature(), "b"), Err(PgpMimeError::InvalidMessageType));
+        assert_eq!(super::build_signed(signed_content(), &pgp_message(), "pgp-sha256", "b"),
+                   Err(PgpMimeError::InvalidMessageType));
+        assert_eq!(super::build_signed(signed_content(), &pgp_signature(), "pgp-sha256", "Hello"),
+                   Err(PgpMimeError::BoundaryInContent));
+
+        for boundary in ["", "b\"", "b\r\nX-Injected: 1", "b ", &"b".repeat(71)].iter() {
+            assert_eq!(super::build_encrypted(&pgp_message(), boundary), Err(PgpMimeError::InvalidBoundary));
+            assert_eq!(super::build_signed(signed_content(), &pgp_signature(), "pgp-sha256", boundary),
+                       Err(PgpMimeError::InvalidBoundary));
+        }
+        assert!(super::build_encrypted(&pgp_message(), "=_Part 1 (b'c+d,e/f:g?h)").is_ok());
+
+        for micalg in ["", "pgp-", "sha256", "pgp-sha256;\r\n boundary=x", "pgp-sha\"256", "pgp-sha 256"].iter() {
+            assert_eq!(super::build_signed(signed_content(), &pgp_signature(), micalg, "b"),
+                       Err(PgpMimeError::InvalidMicalg));
+        }
+        assert!(super::build_signed(signed_content(), &pgp_signature(), "PGP-SHA512", "boundary-2").is_ok());
+    }
+}
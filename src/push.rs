@@ -0,0 +1,482 @@
+use std::cmp;
+use std::mem;
+use std::str;
+use lexer::Lexer;
+use limits::{Limit, Limits};
+use parser::{Parser, MessageType, HeaderType, ParseError, ParseResult};
+use base64;
+use crc24::{Crc24, Crc24Hasher};
+use zeroize::{self, SecretBytes};
+
+
+const FIVE_DASHES: &[u8] = b"-----";
+const BASE64_LINE_LENGTH: usize = 76;
+
+// A line longer than this cannot be part of an armor block, so it is rejected
+// instead of being buffered while its newline is awaited.
+const MAX_LINE_LENGTH: usize = 8192;
+
+
+/// A piece of armor structure reported by the push parser.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Event {
+    Begin(MessageType),
+    Header(HeaderType, String),
+    /// Octets decoded from the body, in order. Each body line yields the whole
+    /// base64 groups that end on it. The octets may be secret key material, so
+    /// they are wiped when the event is dropped.
+    Body(SecretBytes),
+    /// The armor checksum, and whether it matches the CRC-24 of the body.
+    Checksum { checksum: Crc24, valid: bool },
+    End(MessageType)
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum State {
+    HeaderLine,
+    Headers,
+    Body,
+    TailLine,
+    Done,
+    Failed(ParseError)
+}
+
+/// An armor parser that is fed input as it arrives instead of reading it. The
+/// input can be split anywhere, even inside a line or character, and the
+/// parser reports every event it has the data for before asking for more.
+/// Lines may end in CRLF.
+///
+/// Only the start of the current line is buffered between calls, so a block
+/// of any size is parsed in constant memory.
+pub struct PushParser {
+    state: State,
+    message_type: Option<MessageType>,
+    line: Vec<u8>,
+    body_lines: usize,
+    group: [u8; 4],
+    group_length: usize,
+    padded: bool,
+    hasher: Crc24Hasher,
+    limits: Limits,
+    headers: usize,
+    body_length: usize
+}
+
+impl PushParser {
+    pub fn new() -> PushParser {
+        PushParser {
+            state: State::HeaderLine,
+            message_type: None,
+            line: Vec::new(),
+            body_lines: 0,
+            group: [0; 4],
+            group_length: 0,
+            padded: false,
+            hasher: Crc24Hasher::new(),
+            limits: Limits::default(),
+            headers: 0,
+            body_length: 0
+        }
+    }
+
+    /// The resource limits to parse with. Defaults to `Limits::default()`.
+    pub fn limits(mut self, limits: Limits) -> PushParser {
+        self.limits = limits;
+        self
+    }
+
+    /// Tests whether the tail line of the block has been parsed.
+    pub fn is_done(&self) -> bool {
+        self.state == State::Done
+    }
+
+    /// Feed the next piece of input, appending the events it completes to
+    /// `events`. Input after the tail line is ignored. Once an error is
+    /// returned, every later call returns it again.
+    pub fn feed(&mut self, input: &[u8], events: &mut Vec<Event>) -> ParseResult<()> {
+        if let State::Failed(err) = self.state {
+            return Err(err);
+        }
+
+        let result = self.feed_lines(input, events);
+        if let Err(err) = result {
+            self.state = State::Failed(err);
+        }
+
+        result
+    }
+
+    /// Mark the end of the input. The tail line does not need a newline, but
+    /// input that ends before it is an error.
+    pub fn finish(&mut self, events: &mut Vec<Event>) -> ParseResult<()> {
+        match self.state {
+            State::Failed(err) => return Err(err),
+            State::Done => return Ok(()),
+            _ => {}
+        }
+
+        let result = if self.state == State::TailLine && !self.line.is_empty() {
+            let mut line = mem::take(&mut self.line);
+            let result = self.parse_line(&line, events);
+            zeroize::zeroize(&mut line);
+            result
+        } else {
+            Err(ParseError::EndOfFile)
+        };
+        if let Err(err) = result {
+            self.state = State::Failed(err);
+        }
+
+        result
+    }
+
+    fn feed_lines(&mut self, input: &[u8], events: &mut Vec<Event>) -> ParseResult<()> {
+        let mut rest = input;
+        while self.state != State::Done {
+            match rest.iter().position(|&byte| byte == b'\n') {
+                Some(end) => {
+                    if self.line.is_empty() {
+                        self.parse_line(&rest[..end], events)?;
+                    } else {
+                        self.buffer(&rest[..end])?;
+                        let mut line = mem::take(&mut self.line);
+                        let result = self.parse_line(&line, events);
+                        zeroize::zeroize(&mut line);
+                        line.clear();
+                        self.line = line;
+                        result?;
+                    }
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    self.buffer(rest)?;
+                    if self.state == State::TailLine {
+                        self.parse_partial_tail_line(events)?;
+                    }
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Hold on to the start of a line until its newline arrives.
+    fn buffer(&mut self, octets: &[u8]) -> ParseResult<()> {
+        let length = self.line.len() + octets.len();
+        match self.state {
+            // Two more for padding past the limit and a carriage return.
+            State::Body if length > BASE64_LINE_LENGTH + 2 => return Err(ParseError::CorruptBody),
+            _ if length > MAX_LINE_LENGTH => return Err(self.line_error()),
+            _ => {}
+        }
+        zeroize::push_bytes(&mut self.line, octets);
+
+        Ok(())
+    }
+
+    fn line_error(&self) -> ParseError {
+        match self.state {
+            State::Headers => ParseError::InvalidHeaderLine,
+            State::Body => ParseError::CorruptBody,
+            _ => ParseError::CorruptHeader
+        }
+    }
+
+    // The tail line is reported as soon as its closing dashes arrive, since it
+    // may be the last thing a peer sends.
+    fn parse_partial_tail_line(&mut self, events: &mut Vec<Event>) -> ParseResult<()> {
+        if boundary_line_end(&self.line).is_some() {
+            let mut line = mem::take(&mut self.line);
+            let result = self.parse_line(&line, events);
+            zeroize::zeroize(&mut line);
+            result?;
+        }
+
+        Ok(())
+    }
+
+    fn parse_line(&mut self, line: &[u8], events: &mut Vec<Event>) -> ParseResult<()> {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        match self.state {
+            State::HeaderLine => {
+                let (message_type, end) = boundary_line(line, true)?;
+                if line[end..].iter().any(|&byte| byte != b' ') {
+                    return Err(ParseError::CorruptHeader);
+                }
+                events.push(Event::Begin(message_type.clone()));
+                self.message_type = Some(message_type);
+                self.state = State::Headers;
+            }
+            State::Headers => {
+                if line.iter().all(|&byte| byte == b' ') {
+                    self.state = State::Body;
+                } else {
+                    self.headers += 1;
+                    self.limits.check(Limit::Headers, self.headers)?;
+                    let (header_type, value) = header(line, self.limits)?;
+                    events.push(Event::Header(header_type, value));
+                }
+            }
+            State::Body => {
+                if line.starts_with(b"=") {
+                    let checksum = self.parse_checksum_line(line)?;
+                    let valid = self.hasher.finish() == checksum;
+                    events.push(Event::Checksum { checksum, valid });
+                    self.state = State::TailLine;
+                } else if line.starts_with(FIVE_DASHES) {
+                    // The checksum line is missing.
+                    return Err(ParseError::CorruptBody);
+                } else {
+                    self.body_length += line.len();
+                    self.limits.check(Limit::BodyLength, self.body_length)?;
+                    let octets = self.parse_body_line(line).ok_or(ParseError::CorruptBody)?;
+                    self.hasher.update(&octets);
+                    if !octets.is_empty() {
+                        events.push(Event::Body(SecretBytes::new(octets)));
+                    }
+                }
+            }
+            State::TailLine => {
+                let (message_type, _) = boundary_line(line, false)?;
+                if self.message_type.as_ref() != Some(&message_type) {
+                    return Err(ParseError::ParseError);
+                }
+                events.push(Event::End(message_type));
+                self.state = State::Done;
+            }
+            State::Done | State::Failed(_) => {}
+        }
+
+        Ok(())
+    }
+
+    // Decode the base64 groups that end on a body line. Groups may span lines,
+    // and padding may only end the last group of the body.
+    fn parse_body_line(&mut self, line: &[u8]) -> Option<Vec<u8>> {
+        let data = line.iter().take_while(|&&byte| base64::is_base64_byte(byte)).count();
+        let padding = line[data..].iter().take_while(|&&byte| byte == b'=').count();
+        if line.len() > BASE64_LINE_LENGTH || data + padding != line.len() {
+            return None;
+        }
+        if self.padded && !line.is_empty() {
+            return None;
+        }
+        self.body_lines += 1;
+
+        let mut octets = Vec::with_capacity(line.len() / 4 * 3 + 3);
+        let mut rest = line;
+        if self.group_length > 0 {
+            let length = cmp::min(4 - self.group_length, rest.len());
+            self.group[self.group_length..self.group_length + length].copy_from_slice(&rest[..length]);
+            self.group_length += length;
+            rest = &rest[length..];
+            if self.group_length == 4 {
+                self.group_length = 0;
+                base64::decode_bytes_into(&self.group, &mut octets)?;
+                self.padded = self.group[3] == b'=';
+            }
+        }
+
+        let whole = rest.len() / 4 * 4;
+        if whole > 0 {
+            if self.padded {
+                return None;
+            }
+            base64::decode_bytes_into(&rest[..whole], &mut octets)?;
+            self.padded = rest[whole - 1] == b'=';
+        }
+
+        let tail = &rest[whole..];
+        if !tail.is_empty() {
+            if self.padded {
+                return None;
+            }
+            self.group[..tail.len()].copy_from_slice(tail);
+            self.group_length = tail.len();
+        }
+
+        Some(octets)
+    }
+
+    fn parse_checksum_line(&mut self, line: &[u8]) -> ParseResult<Crc24> {
+        // A block needs a body, and the body must end on a whole group.
+        if self.body_lines == 0 || self.group_length > 0 || line.len() != 5 {
+            return Err(ParseError::CorruptBody);
+        }
+
+        let mut octets = Vec::with_capacity(3);
+        base64::decode_bytes_into(&line[1..], &mut octets)
+            .filter(|_| octets.len() == 3)
+            .ok_or(ParseError::CorruptBody)?;
+
+        Ok(octets.iter().fold(0, |crc, &octet| (crc << 8) | octet as Crc24))
+    }
+}
+
+impl Default for PushParser {
+    fn default() -> PushParser {
+        PushParser::new()
+    }
+}
+
+// The line buffer and the partial group may hold secret key material.
+impl Drop for PushParser {
+    fn drop(&mut self) {
+        zeroize::zeroize(&mut self.line);
+        zeroize::zeroize(&mut self.group);
+    }
+}
+
+// The offset just past the closing dashes of a header or tail line.
+fn boundary_line_end(line: &[u8]) -> Option<usize> {
+    if !line.starts_with(FIVE_DASHES) {
+        return None;
+    }
+
+    line[FIVE_DASHES.len()..].windows(FIVE_DASHES.len())
+        .position(|window| window == FIVE_DASHES)
+        .map(|offset| offset + 2 * FIVE_DASHES.len())
+}
+
+// Parse the header or tail line at the start of a line with the token parser.
+// Returns the message type and the offset just past the line.
+fn boundary_line(line: &[u8], header: bool) -> ParseResult<(MessageType, usize)> {
+    let end = boundary_line_end(line).ok_or(ParseError::CorruptHeader)?;
+    let text = str::from_utf8(&line[..end]).map_err(|_| ParseError::CorruptHeader)?;
+    let mut parser = Parser::new(Lexer::new(text));
+    let result = if header { parser.parse_header_line() } else { parser.parse_tail_line() };
+
+    match result {
+        Ok(message_type) => Ok((message_type, end)),
+        // The whole line is here, so running out of it means it is malformed.
+        Err(ParseError::EndOfFile) => Err(ParseError::CorruptHeader),
+        Err(err) => Err(err)
+    }
+}
+
+fn header(line: &[u8], limits: Limits) -> ParseResult<(HeaderType, String)> {
+    let text = str::from_utf8(line).map_err(|_| ParseError::InvalidHeaderLine)?;
+    let text = format!("{}\n", text);
+
+    match Parser::new(Lexer::new(&text)).limits(limits).parse_headerkv() {
+        Err(ParseError::EndOfFile) => Err(ParseError::InvalidHeaderLine),
+        result => result
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use lexer::Lexer;
+    use limits::{Limit, Limits};
+    use parser::{Parser, MessageType, HeaderType, ParseError};
+    use zeroize::SecretBytes;
+    use super::{Event, PushParser};
+
+
+    // Feed text in pieces of the given size and collect the events.
+    fn feed(text: &str, piece: usize) -> (Vec<Event>, Result<(), ParseError>) {
+        let mut parser = PushParser::new();
+        let mut events = Vec::new();
+        for chunk in text.as_bytes().chunks(piece) {
+            if let Err(err) = parser.feed(chunk, &mut events) {
+                return (events, Err(err));
+            }
+        }
+        let result = parser.finish(&mut events);
+
+        (events, result)
+    }
+
+    fn body(events: &[Event]) -> Vec<u8> {
+        events.iter().flat_map(|event| match *event {
+            Event::Body(ref octets) => octets.to_vec(),
+            _ => Vec::new()
+        }).collect()
+    }
+
+    #[test]
+    fn test_push_parser_samples() {
+        for text in [include_str!("../sample/message.asc"),
+                     include_str!("../sample/pub.asc"),
+                     include_str!("../sample/priv.asc")].iter() {
+            let message = Parser::new(Lexer::new(text)).parse().unwrap();
+            for &piece in [1, 7, 64, text.len()].iter() {
+                let (events, result) = feed(text, piece);
+                assert_eq!(result, Ok(()));
+
+                let mut expected = vec![Event::Begin(message.header_type().clone())];
+                expected.extend(message.header_block().iter().cloned().map(|(key, value)| Event::Header(key, value)));
+                let headers = expected.len();
+                assert_eq!(&events[..headers], &expected[..]);
+                assert_eq!(body(&events), message.decode_body().unwrap());
+                assert_eq!(events[events.len() - 2], Event::Checksum {
+                    checksum: message.decode_checksum().unwrap(),
+                    valid: true
+                });
+                assert_eq!(events[events.len() - 1], Event::End(message.header_type().clone()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_push_parser_events_arrive_early() {
+        let mut parser = PushParser::new();
+        let mut events = Vec::new();
+        parser.feed(b"-----BEGIN PGP MESSAGE-----\r\nVersion: 1\r\nComm", &mut events).unwrap();
+        assert_eq!(events, vec![
+            Event::Begin(MessageType::PGPMessage),
+            Event::Header(HeaderType::Version, String::from("1"))
+        ]);
+
+        events.clear();
+        parser.feed(b"ent: x\r\n\r\nZm9v\r\nYg==\r\n=", &mut events).unwrap();
+        assert_eq!(events, vec![
+            Event::Header(HeaderType::Comment, String::from("x")),
+            Event::Body(SecretBytes::new(b"foo".to_vec())),
+            Event::Body(SecretBytes::new(b"b".to_vec()))
+        ]);
+
+        events.clear();
+        parser.feed(b"wRlL\r\n-----END PGP MESSAGE-----", &mut events).unwrap();
+        assert!(parser.is_done());
+        assert_eq!(events[1], Event::End(MessageType::PGPMessage));
+    }
+
+    #[test]
+    fn test_push_parser_errors() {
+        let tests = vec![
+            ("-----BEGIN PGP MESSAGE-----\n\nZm9v\n=wRlL\n-----END PGP SIGNATURE-----\n", ParseError::ParseError),
+            ("-----BEGIN PGP MESSAGE-----\n\nZm9v\n-----END PGP MESSAGE-----\n", ParseError::CorruptBody),
+            ("-----BEGIN PGP MESSAGE-----\n\nZg==\nZm9v\n=wRlL\n", ParseError::CorruptBody),
+            ("-----BEGIN PGP MESSAGE-----\n\nZm9\n=wRlL\n", ParseError::CorruptBody),
+            ("-----BEGIN PGP MESSAGE-----\n\nZm9v\n", ParseError::EndOfFile),
+            ("-----BEGIN PGP MESSAGE\n", ParseError::CorruptHeader),
+            ("-----BEGIN PGP MESSAGE-----\nVersion 1\n", ParseError::InvalidHeaderLine)
+        ];
+
+        for (text, error) in tests {
+            for &piece in [1, 5, text.len()].iter() {
+                assert_eq!(feed(text, piece).1, Err(error));
+            }
+        }
+    }
+
+    #[test]
+    fn test_push_parser_limits() {
+        let text = "-----BEGIN PGP MESSAGE-----\nVersion: 1\nComment: x\n\nZm9v\nYg==\n=wRlL\n-----END PGP MESSAGE-----\n";
+        let tests = [
+            (Limits::new().max_headers(1), ParseError::LimitExceeded(Limit::Headers)),
+            (Limits::new().max_header_length(0), ParseError::LimitExceeded(Limit::HeaderLength)),
+            (Limits::new().max_body_length(7), ParseError::LimitExceeded(Limit::BodyLength))
+        ];
+
+        for &(limits, error) in tests.iter() {
+            let mut parser = PushParser::new().limits(limits);
+            assert_eq!(parser.feed(text.as_bytes(), &mut Vec::new()), Err(error));
+        }
+        let mut parser = PushParser::new().limits(Limits::new().max_headers(2).max_body_length(8));
+        assert_eq!(parser.feed(text.as_bytes(), &mut Vec::new()), Ok(()));
+    }
+}
@@ -0,0 +1,543 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::Read;
+use std::ops::Range;
+use std::str;
+use lexer::Lexer;
+use limits::{Limit, Limits};
+use parser::{Parser, ArmorMessage, ParseError, ParseResult};
+use zeroize;
+
+
+const BEGIN_MARKER: &str = "-----BEGIN PGP ";
+const END_MARKER: &str = "-----END ";
+const FIVE_DASHES: &str = "-----";
+
+// The longest label looked for between the dashes of a header or tail line.
+// The longest real one is a message part with two ten digit numbers.
+const MAX_LABEL_LENGTH: usize = 64;
+
+
+/// An armor block found inside a larger piece of text. The span is the byte
+/// range of the block in the scanned text, from the first dash of the header
+/// line to the last dash of the tail line.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ArmorBlock {
+    span: Range<usize>,
+    message: ArmorMessage
+}
+
+impl ArmorBlock {
+    pub fn new(span: Range<usize>, message: ArmorMessage) -> ArmorBlock {
+        ArmorBlock {
+            span,
+            message
+        }
+    }
+
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    pub fn message(&self) -> &ArmorMessage {
+        &self.message
+    }
+
+    pub fn into_message(self) -> ArmorMessage {
+        self.message
+    }
+}
+
+/// Finds every armor block in a piece of arbitrary text. Anything between the
+/// blocks, and any candidate block that fails to parse, is skipped. The scan
+/// stops early when the text holds more blocks than the limits allow.
+pub struct Scanner<'a> {
+    text: &'a str,
+    position: usize,
+    strip_quotes: bool,
+    limits: Limits,
+    blocks: usize,
+    error: Option<ParseError>,
+    tail_lines: Option<HashMap<&'a str, Vec<usize>>>
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(text: &'a str) -> Scanner<'a> {
+        Scanner {
+            text,
+            position: 0,
+            strip_quotes: false,
+            limits: Limits::default(),
+            blocks: 0,
+            error: None,
+            tail_lines: None
+        }
+    }
+
+    /// The resource limits to parse each block with, and to bound the number
+    /// of blocks by. Defaults to `Limits::default()`.
+    pub fn limits(mut self, limits: Limits) -> Scanner<'a> {
+        self.limits = limits;
+        self
+    }
+
+    /// The error that stopped the scan early, once there were more blocks
+    /// than the limits allow. A caller that collects the blocks should check
+    /// it, since the blocks after the limit are not yielded.
+    pub fn error(&self) -> Option<ParseError> {
+        self.error
+    }
+
+    /// Remove email quoting from quoted armor blocks. When enabled, the quote
+    /// prefix in front of the BEGIN marker, such as `> ` or `>> `, must be present
+    /// on every following line of the block up to the END line, and is removed
+    /// from each of them before the block is parsed.
+    pub fn strip_quotes(mut self, strip_quotes: bool) -> Scanner<'a> {
+        self.strip_quotes = strip_quotes;
+        self
+    }
+
+    // The quote prefix is everything on the header line in front of the BEGIN
+    // marker, provided it consists only of quote markers and spaces.
+    fn quote_prefix(&self, start: usize) -> Option<&'a str> {
+        let line_start = self.text[..start].rfind('\n').map_or(0, |i| i + 1);
+        let prefix = &self.text[line_start..start];
+        if prefix.contains('>') && prefix.chars().all(|ch| ch == '>' || ch == ' ') {
+            Some(prefix)
+        } else {
+            None
+        }
+    }
+
+    fn parse_candidate(&self, span: Range<usize>) -> ParseResult<ArmorMessage> {
+        let text = &self.text[span.clone()];
+        match self.quote_prefix(span.start) {
+            Some(prefix) if self.strip_quotes => {
+                match unquote(text, prefix) {
+                    Some(mut unquoted) => {
+                        let result = parse_armor(&unquoted, self.limits);
+                        zeroize::zeroize_string(&mut unquoted);
+                        result
+                    }
+                    None => Err(ParseError::CorruptHeader)
+                }
+            }
+            _ => parse_armor(text, self.limits)
+        }
+    }
+
+    // Locate the next region starting with a header line and ending with the
+    // matching tail line. Returns the span of the region and the offset just past
+    // the header line, where scanning resumes if the region does not parse.
+    fn next_candidate(&mut self) -> Option<(Range<usize>, usize)> {
+        let text = self.text;
+        let tail_lines = self.tail_lines.get_or_insert_with(|| find_tail_lines(text));
+        let mut position = self.position;
+        loop {
+            let start = position + text[position..].find(BEGIN_MARKER)?;
+            let label_start = start + FIVE_DASHES.len() + "BEGIN ".len();
+            let label_end = match find_label_end(text, label_start) {
+                Some(label_end) => label_end,
+                None => {
+                    position = label_start;
+                    continue;
+                }
+            };
+            position = label_end + FIVE_DASHES.len();
+
+            // The first tail line with the same label after the header line. A
+            // block cannot hold another header line, so when one comes first
+            // the scan moves on to it, and every region is parsed only once.
+            let label = &text[label_start..label_end];
+            let next_start = text[position..].find(BEGIN_MARKER).map_or(text.len(), |i| position + i);
+            if let Some(offsets) = tail_lines.get(label) {
+                let i = match offsets.binary_search(&label_end) {
+                    Ok(i) | Err(i) => i
+                };
+                match offsets.get(i) {
+                    Some(&tail_start) if tail_start < next_start => {
+                        let end = tail_start + END_MARKER.len() + label.len() + FIVE_DASHES.len();
+                        return Some((start..end, position));
+                    }
+                    _ => {}
+                }
+            }
+            position = next_start;
+        }
+    }
+}
+
+impl<'a> Iterator for Scanner<'a> {
+    type Item = ArmorBlock;
+
+    fn next(&mut self) -> Option<ArmorBlock> {
+        while let Some((span, resume)) = self.next_candidate() {
+            match self.parse_candidate(span.clone()) {
+                Ok(message) => {
+                    self.blocks += 1;
+                    if let Err(err) = self.limits.check(Limit::Blocks, self.blocks) {
+                        self.error = Some(err);
+                        break;
+                    }
+                    self.position = span.end;
+                    return Some(ArmorBlock::new(span, message));
+                }
+                Err(_) => {
+                    self.position = resume;
+                }
+            }
+        }
+
+        self.position = self.text.len();
+        None
+    }
+}
+
+// The offset of the dashes that close the label starting at an offset, when
+// they are on the same line and not too far away.
+fn find_label_end(text: &str, label_start: usize) -> Option<usize> {
+    let bytes = &text.as_bytes()[label_start..];
+    let bytes = &bytes[..bytes.len().min(MAX_LABEL_LENGTH + FIVE_DASHES.len())];
+    let bytes = bytes.split(|&byte| byte == b'\n').next().unwrap_or(bytes);
+
+    bytes.windows(FIVE_DASHES.len())
+         .position(|window| window == FIVE_DASHES.as_bytes())
+         .map(|i| label_start + i)
+}
+
+// The offsets of the tail lines of the text by label, in order. They are found
+// in one pass so that a header line without a tail line does not search the
+// rest of the text again.
+fn find_tail_lines(text: &str) -> HashMap<&str, Vec<usize>> {
+    let mut tail_lines: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (start, _) in text.match_indices(END_MARKER) {
+        let label_start = start + END_MARKER.len();
+        if let Some(label_end) = find_label_end(text, label_start) {
+            tail_lines.entry(&text[label_start..label_end]).or_default().push(start);
+        }
+    }
+
+    tail_lines
+}
+
+// Remove the quote prefix from every line after the header line. Quoted blank
+// lines often carry the prefix without its trailing spaces.
+fn unquote(text: &str, prefix: &str) -> Option<String> {
+    let bare_prefix = prefix.trim_end();
+    let mut lines = text.split('\n');
+    let mut result = String::with_capacity(text.len());
+    result.push_str(lines.next()?);
+
+    for line in lines {
+        result.push('\n');
+        match (line.strip_prefix(prefix), line.strip_prefix(bare_prefix)) {
+            (Some(rest), _) => result.push_str(rest),
+            (None, Some(rest)) if rest.trim().is_empty() => result.push_str(rest),
+            _ => return None
+        }
+    }
+
+    Some(result)
+}
+
+// Text pasted from mail and web pages frequently uses CRLF line endings. The
+// normalized copy is wiped since it may hold a private key block.
+fn parse_armor(text: &str, limits: Limits) -> ParseResult<ArmorMessage> {
+    if text.contains('\r') {
+        let mut normalized = text.replace("\r\n", "\n");
+        let result = Parser::new(Lexer::new(&normalized)).limits(limits).parse();
+        zeroize::zeroize_string(&mut normalized);
+        result
+    } else {
+        Parser::new(Lexer::new(text)).limits(limits).parse()
+    }
+}
+
+/// Scan a string for armor blocks.
+pub fn scan(text: &str) -> Scanner<'_> {
+    Scanner::new(text)
+}
+
+// Input decoded as UTF-8 with every invalid sequence replaced by U+FFFD,
+// which takes up more bytes than most of the sequences it replaces. The
+// offsets just past each replacement, in the text and in the input, are kept
+// so that offsets in the text can be mapped back to the input.
+struct LossyText {
+    text: String,
+    replacements: Vec<(usize, usize)>
+}
+
+impl LossyText {
+    fn new(octets: &[u8]) -> LossyText {
+        let mut text = String::with_capacity(octets.len());
+        let mut replacements = Vec::new();
+        let mut rest = octets;
+        loop {
+            match str::from_utf8(rest) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    break;
+                }
+                Err(err) => {
+                    let (valid, invalid) = rest.split_at(err.valid_up_to());
+                    text.push_str(str::from_utf8(valid).expect("the prefix is valid UTF-8"));
+                    text.push(char::REPLACEMENT_CHARACTER);
+                    rest = &invalid[err.error_len().unwrap_or(invalid.len())..];
+                    replacements.push((text.len(), octets.len() - rest.len()));
+                }
+            }
+        }
+
+        LossyText { text, replacements }
+    }
+
+    fn input_offset(&self, offset: usize) -> usize {
+        let before = self.replacements.partition_point(|&(text_offset, _)| text_offset <= offset);
+        self.replacements[..before].last()
+                                   .map_or(offset, |&(text_offset, input_offset)| input_offset + offset - text_offset)
+    }
+}
+
+fn limit_error(err: ParseError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// Read all of the input to scan it. Input longer than the limits allow is an
+/// `InvalidData` error.
+pub fn read_input<R: Read>(reader: R, limits: Limits) -> io::Result<Vec<u8>> {
+    let length = limits.limit(Limit::InputLength);
+    let mut octets = Vec::new();
+    reader.take(length.saturating_add(1) as u64).read_to_end(&mut octets)?;
+    limits.check(Limit::InputLength, octets.len()).map_err(limit_error)?;
+
+    Ok(octets)
+}
+
+/// Read all of the input and scan it for armor blocks, appending them to
+/// `blocks`. Bytes of the surrounding text that are not valid UTF-8 are
+/// replaced before scanning, and the spans of the blocks are byte ranges of
+/// the input. More input or more blocks than the default limits allow is an
+/// `InvalidData` error; on such an error `blocks` holds the blocks found
+/// before the limit was reached.
+pub fn scan_reader<R: Read>(reader: R, blocks: &mut Vec<ArmorBlock>) -> io::Result<()> {
+    let octets = read_input(reader, Limits::default())?;
+    let lossy = LossyText::new(&octets);
+
+    let mut scanner = scan(&lossy.text);
+    blocks.extend(scanner.by_ref().map(|block| {
+        let span = lossy.input_offset(block.span.start)..lossy.input_offset(block.span.end);
+        ArmorBlock::new(span, block.message)
+    }));
+
+    match scanner.error() {
+        Some(err) => Err(limit_error(err)),
+        None => Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, ErrorKind};
+    use limits::{Limit, Limits};
+    use parser::{MessageType, ParseError};
+
+
+    struct TestCase {
+        text: String,
+        blocks: Vec<(MessageType, String)>
+    }
+
+    struct Test {
+        data: Vec<TestCase>
+    }
+
+    fn message_block() -> &'static str {
+        "-----BEGIN PGP MESSAGE-----\n\
+         Version: OpenPrivacy 0.99\n\
+         \n\
+         yDgBO22WxBHv7O8X7O/jygAEzol56iUKiXmV+XmpCtmpqQUKiQrFqclFqUDBovzS\n\
+         vBSFjNSiVHsuAA==\n\
+         =njUN\n\
+         -----END PGP MESSAGE-----"
+    }
+
+    fn signature_block() -> &'static str {
+        "-----BEGIN PGP SIGNATURE-----\n\
+         \n\
+         vBSFjNSiVHsuAA==\n\
+         =njUN\n\
+         -----END PGP SIGNATURE-----"
+    }
+
+    fn scanner_test_cases() -> Test {
+        Test {
+            data: vec![
+                TestCase {
+                    text: String::from(message_block()),
+                    blocks: vec![(MessageType::PGPMessage, String::from(message_block()))]
+                },
+                TestCase {
+                    text: format!("Hi Bob,\n\nhere is the message:\n\n{}\n\nCheers,\nAlice\n", message_block()),
+                    blocks: vec![(MessageType::PGPMessage, String::from(message_block()))]
+                },
+                TestCase {
+                    text: format!("First: {}\nand second:\n{}\n-- \nsig", message_block(), signature_block()),
+                    blocks: vec![
+                        (MessageType::PGPMessage, String::from(message_block())),
+                        (MessageType::PGPSignature, String::from(signature_block()))
+                    ]
+                },
+                TestCase {
+                    text: format!("-----BEGIN PGP MESSAGE----- was cut off here.\n{}", signature_block()),
+                    blocks: vec![(MessageType::PGPSignature, String::from(signature_block()))]
+                },
+                TestCase {
+                    text: format!("-----BEGIN PGP SIGNATURE-----\ngarbage\n-----END PGP SIGNATURE-----\n{}",
+                                  message_block()),
+                    blocks: vec![(MessageType::PGPMessage, String::from(message_block()))]
+                },
+                TestCase {
+                    text: String::from("No armor here, just -----BEGIN PGP prose-----."),
+                    blocks: vec![]
+                }
+            ]
+        }
+    }
+
+    fn run_tests(test: &Test) {
+        for test_case in test.data.iter() {
+            let blocks: Vec<_> = super::scan(&test_case.text).collect();
+            assert_eq!(blocks.len(), test_case.blocks.len());
+            for (block, (message_type, text)) in blocks.iter().zip(test_case.blocks.iter()) {
+                assert_eq!(block.message().header_type(), message_type);
+                assert_eq!(&test_case.text[block.span()], text.as_str());
+            }
+        }
+    }
+
+    #[test]
+    fn test_scanner() {
+        run_tests(&scanner_test_cases());
+    }
+
+    #[test]
+    fn test_scanner_crlf_line_endings() {
+        let text = format!("Hello\r\n{}\r\n", message_block().replace("\n", "\r\n"));
+        let blocks: Vec<_> = super::scan(&text).collect();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].message().body(), "yDgBO22WxBHv7O8X7O/jygAEzol56iUKiXmV+XmpCtmpqQUKiQrFqclFqUDBovzSvBSFjNSiVHsuAA==");
+        assert!(text[blocks[0].span()].ends_with("-----END PGP MESSAGE-----"));
+    }
+
+    fn quoted_message_block(prefix: &str) -> String {
+        let mut text = String::new();
+        for line in message_block().lines() {
+            if line.is_empty() {
+                text.push_str(prefix.trim_end());
+            } else {
+                text.push_str(prefix);
+                text.push_str(line);
+            }
+            text.push('\n');
+        }
+
+        text
+    }
+
+    #[test]
+    fn test_scanner_strip_quotes() {
+        for prefix in ["> ", ">> ", "> > "].iter() {
+            let text = format!("On Monday, Alice wrote:\n{}\nThanks!\n", quoted_message_block(prefix));
+            let blocks: Vec<_> = super::scan(&text).strip_quotes(true).collect();
+            assert_eq!(blocks.len(), 1);
+            assert_eq!(blocks[0].message().header_type(), &MessageType::PGPMessage);
+            assert_eq!(blocks[0].message().checksum(), "njUN");
+            assert!(text[blocks[0].span()].starts_with("-----BEGIN PGP MESSAGE-----"));
+            assert!(text[blocks[0].span()].ends_with("-----END PGP MESSAGE-----"));
+        }
+    }
+
+    #[test]
+    fn test_scanner_quoted_block_without_strip_quotes() {
+        let text = quoted_message_block("> ");
+        assert_eq!(super::scan(&text).count(), 0);
+    }
+
+    #[test]
+    fn test_scanner_inconsistent_quote_prefix() {
+        let text = quoted_message_block("> ").replace("> =njUN", ">> =njUN");
+        assert_eq!(super::scan(&text).strip_quotes(true).count(), 0);
+    }
+
+    fn scan_reader(octets: Vec<u8>) -> Vec<super::ArmorBlock> {
+        let mut blocks = Vec::new();
+        super::scan_reader(Cursor::new(octets), &mut blocks).unwrap();
+        blocks
+    }
+
+    #[test]
+    fn test_scan_reader() {
+        let text = format!("Subject: key\n\n{}\n", message_block());
+        let blocks = scan_reader(text.into_bytes());
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].span(), 14..(14 + message_block().len()));
+    }
+
+    #[test]
+    fn test_scan_reader_invalid_utf8() {
+        let mut octets = b"Subject: \xff\xfe\n\n".to_vec();
+        octets.extend_from_slice(message_block().as_bytes());
+        octets.extend_from_slice(b"\n\xe2\x82\n");
+        octets.extend_from_slice(signature_block().as_bytes());
+        octets.extend_from_slice(b"\n\xc3");
+        let blocks = scan_reader(octets.clone());
+        let first = 13..(13 + message_block().len());
+        let second = (first.end + 4)..(first.end + 4 + signature_block().len());
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].span(), first);
+        assert_eq!(blocks[1].span(), second);
+        assert_eq!(&octets[first], message_block().as_bytes());
+        assert_eq!(&octets[second], signature_block().as_bytes());
+        assert_eq!(blocks[0].message().checksum(), "njUN");
+    }
+
+    #[test]
+    fn test_scanner_unterminated_header_lines() {
+        // Each header line without a tail line is passed over without
+        // searching the rest of the text again.
+        let text = format!("{}{}\n", "-----BEGIN PGP MESSAGE-----\nxyz\n".repeat(50000), message_block());
+        let blocks: Vec<_> = super::scan(&text).collect();
+        assert_eq!(blocks.len(), 1);
+        assert!(text[blocks[0].span()].starts_with(message_block()));
+    }
+
+    #[test]
+    fn test_scanner_block_limit() {
+        let text = format!("{}\n{}\n{}\n", message_block(), signature_block(), message_block());
+        let mut scanner = super::scan(&text).limits(Limits::new().max_blocks(2));
+        assert_eq!(scanner.by_ref().count(), 2);
+        assert_eq!(scanner.error(), Some(ParseError::LimitExceeded(Limit::Blocks)));
+
+        let mut scanner = super::scan(&text).limits(Limits::new().max_blocks(3));
+        assert_eq!(scanner.by_ref().count(), 3);
+        assert_eq!(scanner.error(), None);
+
+        let text = format!("{}\n", message_block()).repeat(1100);
+        let mut scanner = super::scan(&text);
+        assert_eq!(scanner.by_ref().count(), 1100);
+        assert_eq!(scanner.error(), None);
+        assert_eq!(scan_reader(text.into_bytes()).len(), 1100);
+    }
+
+    #[test]
+    fn test_read_input_limit() {
+        let limits = Limits::new().max_input_length(4);
+        assert_eq!(super::read_input(Cursor::new(b"abcd".to_vec()), limits).unwrap(), b"abcd");
+
+        let err = super::read_input(Cursor::new(b"abcde".to_vec()), limits).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), ParseError::LimitExceeded(Limit::InputLength).to_string());
+    }
+}
@@ -0,0 +1,635 @@
+use std::error;
+use std::fmt;
+use key::{Fingerprint, KeyId, PublicKeyAlgorithm};
+use packet::{self, Packet, PacketTag, PacketError};
+use parser::{ArmorMessage, MessageType, HeaderType};
+use scanner;
+
+
+const CLEARTEXT_BEGIN: &str = "-----BEGIN PGP SIGNED MESSAGE-----";
+const SIGNATURE_BEGIN: &str = "-----BEGIN PGP SIGNATURE-----";
+
+// Signature subpacket types from section 5.2.3.7 of RFC9580.
+const SUBPACKET_CREATION_TIME: u8 = 2;
+const SUBPACKET_ISSUER: u8 = 16;
+const SUBPACKET_ISSUER_FINGERPRINT: u8 = 33;
+
+
+/// The hash algorithms from section 9.5 of RFC9580.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Ripemd160,
+    Sha256,
+    Sha384,
+    Sha512,
+    Sha224,
+    Sha3_256,
+    Sha3_512,
+    Other(u8)
+}
+
+impl HashAlgorithm {
+    pub fn from_u8(algorithm: u8) -> HashAlgorithm {
+        match algorithm {
+            1  => HashAlgorithm::Md5,
+            2  => HashAlgorithm::Sha1,
+            3  => HashAlgorithm::Ripemd160,
+            8  => HashAlgorithm::Sha256,
+            9  => HashAlgorithm::Sha384,
+            10 => HashAlgorithm::Sha512,
+            11 => HashAlgorithm::Sha224,
+            12 => HashAlgorithm::Sha3_256,
+            14 => HashAlgorithm::Sha3_512,
+            other => HashAlgorithm::Other(other)
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            HashAlgorithm::Md5 => 1,
+            HashAlgorithm::Sha1 => 2,
+            HashAlgorithm::Ripemd160 => 3,
+            HashAlgorithm::Sha256 => 8,
+            HashAlgorithm::Sha384 => 9,
+            HashAlgorithm::Sha512 => 10,
+            HashAlgorithm::Sha224 => 11,
+            HashAlgorithm::Sha3_256 => 12,
+            HashAlgorithm::Sha3_512 => 14,
+            HashAlgorithm::Other(algorithm) => algorithm
+        }
+    }
+
+    /// The text name of the algorithm used in the `Hash` armor header.
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            HashAlgorithm::Md5 => Some("MD5"),
+            HashAlgorithm::Sha1 => Some("SHA1"),
+            HashAlgorithm::Ripemd160 => Some("RIPEMD160"),
+            HashAlgorithm::Sha256 => Some("SHA256"),
+            HashAlgorithm::Sha384 => Some("SHA384"),
+            HashAlgorithm::Sha512 => Some("SHA512"),
+            HashAlgorithm::Sha224 => Some("SHA224"),
+            HashAlgorithm::Sha3_256 => Some("SHA3-256"),
+            HashAlgorithm::Sha3_512 => Some("SHA3-512"),
+            HashAlgorithm::Other(_) => None
+        }
+    }
+
+    /// Look up a hash algorithm by its name, ignoring case.
+    pub fn from_name(name: &str) -> Option<HashAlgorithm> {
+        [1, 2, 3, 8, 9, 10, 11, 12, 14].iter()
+                                       .map(|&algorithm| HashAlgorithm::from_u8(algorithm))
+                                       .find(|algorithm| {
+                                           algorithm.name().map_or(false, |known| known.eq_ignore_ascii_case(name))
+                                       })
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "hash algorithm {}", self.to_u8())
+        }
+    }
+}
+
+/// The signature types from section 5.2.1 of RFC9580.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SignatureType {
+    Binary,
+    Text,
+    Standalone,
+    GenericCertification,
+    PersonaCertification,
+    CasualCertification,
+    PositiveCertification,
+    SubkeyBinding,
+    PrimaryKeyBinding,
+    DirectKey,
+    KeyRevocation,
+    SubkeyRevocation,
+    CertificationRevocation,
+    Timestamp,
+    ThirdPartyConfirmation,
+    Other(u8)
+}
+
+impl SignatureType {
+    pub fn from_u8(signature_type: u8) -> SignatureType {
+        match signature_type {
+            0x00 => SignatureType::Binary,
+            0x01 => SignatureType::Text,
+            0x02 => SignatureType::Standalone,
+            0x10 => SignatureType::GenericCertification,
+            0x11 => SignatureType::PersonaCertification,
+            0x12 => SignatureType::CasualCertification,
+            0x13 => SignatureType::PositiveCertification,
+            0x18 => SignatureType::SubkeyBinding,
+            0x19 => SignatureType::PrimaryKeyBinding,
+            0x1F => SignatureType::DirectKey,
+            0x20 => SignatureType::KeyRevocation,
+            0x28 => SignatureType::SubkeyRevocation,
+            0x30 => SignatureType::CertificationRevocation,
+            0x40 => SignatureType::Timestamp,
+            0x50 => SignatureType::ThirdPartyConfirmation,
+            other => SignatureType::Other(other)
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            SignatureType::Binary => 0x00,
+            SignatureType::Text => 0x01,
+            SignatureType::Standalone => 0x02,
+            SignatureType::GenericCertification => 0x10,
+            SignatureType::PersonaCertification => 0x11,
+            SignatureType::CasualCertification => 0x12,
+            SignatureType::PositiveCertification => 0x13,
+            SignatureType::SubkeyBinding => 0x18,
+            SignatureType::PrimaryKeyBinding => 0x19,
+            SignatureType::DirectKey => 0x1F,
+            SignatureType::KeyRevocation => 0x20,
+            SignatureType::SubkeyRevocation => 0x28,
+            SignatureType::CertificationRevocation => 0x30,
+            SignatureType::Timestamp => 0x40,
+            SignatureType::ThirdPartyConfirmation => 0x50,
+            SignatureType::Other(signature_type) => signature_type
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SignatureError {
+    CorruptBody,
+    MalformedPacket(PacketError),
+    NotASignature(PacketTag),
+    UnsupportedVersion(u8),
+    TruncatedSignature,
+    MalformedSignature,
+    MalformedCleartext,
+    UnknownHashHeader,
+    HashMismatch { offset: usize, algorithm: HashAlgorithm }
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SignatureError::CorruptBody => write!(f, "Corrupt Base64 data."),
+            SignatureError::MalformedPacket(e) => write!(f, "Malformed packet: {}", e),
+            SignatureError::NotASignature(tag) => write!(f, "A {:?} packet is not a signature packet.", tag),
+            SignatureError::UnsupportedVersion(version) => write!(f, "Unsupported signature version {}.", version),
+            SignatureError::TruncatedSignature => write!(f, "Truncated signature packet."),
+            SignatureError::MalformedSignature => write!(f, "Malformed signature packet."),
+            SignatureError::MalformedCleartext => write!(f, "Malformed cleartext signed message."),
+            SignatureError::UnknownHashHeader => write!(f, "Unknown hash algorithm in Hash header."),
+            SignatureError::HashMismatch { offset, algorithm } => {
+                write!(f, "Signature at offset {} uses {}, which the Hash header does not list.", offset, algorithm)
+            }
+        }
+    }
+}
+
+impl error::Error for SignatureError {
+    fn description(&self) -> &str {
+        match *self {
+            SignatureError::CorruptBody => "The Base 64 payload of the armor message was corrupted.",
+            SignatureError::MalformedPacket(_) => "The packet stream in the armor body is malformed.",
+            SignatureError::NotASignature(_) => "The packet is not a signature packet.",
+            SignatureError::UnsupportedVersion(_) => "Only version 3, 4 and 6 signatures can be read.",
+            SignatureError::TruncatedSignature => "The signature packet ends before its metadata.",
+            SignatureError::MalformedSignature => "A field of the signature packet has a value that is not allowed.",
+            SignatureError::MalformedCleartext => "The cleartext signature framework is malformed.",
+            SignatureError::UnknownHashHeader => "The Hash header names a hash algorithm that is not known.",
+            SignatureError::HashMismatch { .. } => "A signature uses a hash algorithm not listed in the Hash header."
+        }
+    }
+}
+
+pub type SignatureResult<T> = Result<T, SignatureError>;
+
+/// The metadata of a signature packet. The creation time and issuer come
+/// from the subpackets of version 4 and 6 signatures, hashed subpackets
+/// taking precedence over unhashed ones.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct SignatureInfo {
+    offset: usize,
+    version: u8,
+    signature_type: SignatureType,
+    public_key_algorithm: PublicKeyAlgorithm,
+    hash_algorithm: HashAlgorithm,
+    created: Option<u32>,
+    issuer: Option<KeyId>,
+    issuer_fingerprint: Option<Fingerprint>
+}
+
+impl SignatureInfo {
+    /// The offset of the signature packet in the packet stream.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn signature_type(&self) -> SignatureType {
+        self.signature_type
+    }
+
+    pub fn public_key_algorithm(&self) -> PublicKeyAlgorithm {
+        self.public_key_algorithm
+    }
+
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// The creation time in seconds since the Unix epoch.
+    pub fn created(&self) -> Option<u32> {
+        self.created
+    }
+
+    pub fn issuer(&self) -> Option<KeyId> {
+        self.issuer
+    }
+
+    pub fn issuer_fingerprint(&self) -> Option<Fingerprint> {
+        self.issuer_fingerprint
+    }
+}
+
+// A cursor over the body of a signature packet.
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize
+}
+
+impl<'a> Reader<'a> {
+    fn read(&mut self, length: usize) -> SignatureResult<&'a [u8]> {
+        if length > self.data.len() - self.position {
+            return Err(SignatureError::TruncatedSignature);
+        }
+        let octets = &self.data[self.position..self.position + length];
+        self.position += length;
+
+        Ok(octets)
+    }
+
+    fn read_octet(&mut self) -> SignatureResult<u8> {
+        self.read(1).map(|octets| octets[0])
+    }
+
+    fn read_number(&mut self, length: usize) -> SignatureResult<usize> {
+        Ok(self.read(length)?.iter().fold(0, |number, &octet| (number << 8) | octet as usize))
+    }
+
+    // Section 5.2.3.7 of RFC9580.
+    fn read_subpacket_length(&mut self) -> SignatureResult<usize> {
+        let first = self.read_octet()? as usize;
+        match first {
+            0..=191 => Ok(first),
+            192..=254 => Ok(((first - 192) << 8) + self.read_octet()? as usize + 192),
+            _ => self.read_number(4)
+        }
+    }
+}
+
+fn read_subpackets(area: &[u8], info: &mut SignatureInfo) -> SignatureResult<()> {
+    let mut reader = Reader { data: area, position: 0 };
+    while reader.position < area.len() {
+        let length = reader.read_subpacket_length()?;
+        let subpacket = reader.read(length)?;
+        let (&subpacket_type, data) = subpacket.split_first().ok_or(SignatureError::TruncatedSignature)?;
+
+        // The high bit of the type marks a critical subpacket.
+        match subpacket_type & 0x7F {
+            SUBPACKET_CREATION_TIME if data.len() == 4 && info.created.is_none() => {
+                info.created = Some(u32::from_be_bytes([data[0], data[1], data[2], data[3]]));
+            }
+            SUBPACKET_ISSUER if data.len() == 8 && info.issuer.is_none() => {
+                let mut key_id = [0; 8];
+                key_id.copy_from_slice(data);
+                info.issuer = Some(KeyId::new(key_id));
+            }
+            SUBPACKET_ISSUER_FINGERPRINT if info.issuer_fingerprint.is_none() => {
+                info.issuer_fingerprint = match (data.first(), data.len()) {
+                    (Some(&4), 21) => {
+                        let mut fingerprint = [0; 20];
+                        fingerprint.copy_from_slice(&data[1..]);
+                        Some(Fingerprint::V4(fingerprint))
+                    }
+                    (Some(&6), 33) => {
+                        let mut fingerprint = [0; 32];
+                        fingerprint.copy_from_slice(&data[1..]);
+                        Some(Fingerprint::V6(fingerprint))
+                    }
+                    _ => None
+                };
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Read the metadata of a signature packet.
+pub fn signature_info(packet: &Packet) -> SignatureResult<SignatureInfo> {
+    if packet.tag() != PacketTag::Signature {
+        return Err(SignatureError::NotASignature(packet.tag()));
+    }
+
+    let mut reader = Reader { data: packet.body(), position: 0 };
+    let version = reader.read_octet()?;
+    let mut info = SignatureInfo {
+        offset: packet.offset(),
+        version,
+        signature_type: SignatureType::Other(0),
+        public_key_algorithm: PublicKeyAlgorithm::Other(0),
+        hash_algorithm: HashAlgorithm::Other(0),
+        created: None,
+        issuer: None,
+        issuer_fingerprint: None
+    };
+
+    match version {
+        // Version 3 signatures keep the creation time and issuer in fixed fields.
+        2 | 3 => {
+            // The hashed material of a version 3 signature is always five octets.
+            if reader.read_octet()? != 5 {
+                return Err(SignatureError::MalformedSignature);
+            }
+            info.signature_type = SignatureType::from_u8(reader.read_octet()?);
+            info.created = Some(reader.read_number(4)? as u32);
+            let mut key_id = [0; 8];
+            key_id.copy_from_slice(reader.read(8)?);
+            info.issuer = Some(KeyId::new(key_id));
+            info.public_key_algorithm = PublicKeyAlgorithm::from_u8(reader.read_octet()?);
+            info.hash_algorithm = HashAlgorithm::from_u8(reader.read_octet()?);
+        }
+        4 | 6 => {
+            // Version 6 signatures use four octet subpacket area lengths.
+            let count_length = if version == 4 { 2 } else { 4 };
+            info.signature_type = SignatureType::from_u8(reader.read_octet()?);
+            info.public_key_algorithm = PublicKeyAlgorithm::from_u8(reader.read_octet()?);
+            info.hash_algorithm = HashAlgorithm::from_u8(reader.read_octet()?);
+            let hashed_length = reader.read_number(count_length)?;
+            let hashed = reader.read(hashed_length)?;
+            let unhashed_length = reader.read_number(count_length)?;
+            let unhashed = reader.read(unhashed_length)?;
+            read_subpackets(hashed, &mut info)?;
+            read_subpackets(unhashed, &mut info)?;
+        }
+        other => return Err(SignatureError::UnsupportedVersion(other))
+    }
+
+    Ok(info)
+}
+
+/// The metadata of the signature packets in a packet stream, in stream order.
+pub fn signatures(data: &[u8]) -> SignatureResult<Vec<SignatureInfo>> {
+    let mut signatures = Vec::new();
+    for packet in packet::packets(data) {
+        let packet = packet.map_err(SignatureError::MalformedPacket)?;
+        if packet.tag() == PacketTag::Signature {
+            signatures.push(signature_info(&packet)?);
+        }
+    }
+
+    Ok(signatures)
+}
+
+/// The metadata of the signature packets in the body of an armor message.
+pub fn message_signatures(message: &ArmorMessage) -> SignatureResult<Vec<SignatureInfo>> {
+    let data = message.decode_secret_body().ok_or(SignatureError::CorruptBody)?;
+    signatures(&data)
+}
+
+/// Check that every signature uses a hash algorithm listed in the values of
+/// the `Hash` armor headers. Each value is a comma separated list of names.
+/// Without a `Hash` header there is nothing to check.
+pub fn check_hash_headers<'a, I>(hash_headers: I, signatures: &[SignatureInfo]) -> SignatureResult<()>
+    where I: IntoIterator<Item = &'a str>
+{
+    let mut listed = Vec::new();
+    for value in hash_headers {
+        for name in value.split(',').map(str::trim) {
+            listed.push(HashAlgorithm::from_name(name).ok_or(SignatureError::UnknownHashHeader)?);
+        }
+    }
+
+    if listed.is_empty() {
+        return Ok(());
+    }
+
+    match signatures.iter().find(|signature| !listed.contains(&signature.hash_algorithm())) {
+        Some(signature) => Err(SignatureError::HashMismatch {
+            offset: signature.offset(),
+            algorithm: signature.hash_algorithm()
+        }),
+        None => Ok(())
+    }
+}
+
+fn hash_header_values(message: &ArmorMessage) -> Vec<&str> {
+    message.header_block()
+           .iter()
+           .filter(|(header_type, _)| *header_type == HeaderType::Hash)
+           .map(|(_, value)| value.as_str())
+           .collect()
+}
+
+/// Read the signatures of a detached signature block and cross-check their
+/// hash algorithms against the `Hash` armor headers of the block.
+pub fn check_signature_block(message: &ArmorMessage) -> SignatureResult<Vec<SignatureInfo>> {
+    let signatures = message_signatures(message)?;
+    check_hash_headers(hash_header_values(message), &signatures)?;
+
+    Ok(signatures)
+}
+
+/// A message using the cleartext signature framework of section 7 of RFC9580.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CleartextMessage {
+    hash_headers: Vec<String>,
+    text: String,
+    signature: ArmorMessage
+}
+
+impl CleartextMessage {
+    /// The values of the `Hash` headers of the cleartext.
+    pub fn hash_headers(&self) -> &[String] {
+        &self.hash_headers
+    }
+
+    /// The signed text with dash escaping removed. The line break before the
+    /// signature is not part of the signed text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn signature(&self) -> &ArmorMessage {
+        &self.signature
+    }
+
+    /// Read the signatures and cross-check their hash algorithms against the
+    /// `Hash` headers of the cleartext.
+    pub fn check(&self) -> SignatureResult<Vec<SignatureInfo>> {
+        let signatures = message_signatures(&self.signature)?;
+        check_hash_headers(self.hash_headers.iter().map(String::as_str), &signatures)?;
+
+        Ok(signatures)
+    }
+}
+
+/// Parse a cleartext signed message, starting at its `BEGIN PGP SIGNED
+/// MESSAGE` line.
+pub fn parse_cleartext(text: &str) -> SignatureResult<CleartextMessage> {
+    let mut lines = text.split('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len() + 1;
+        Some((start, line.trim_end_matches('\r')))
+    });
+    match lines.next() {
+        Some((_, line)) if line.trim_end() == CLEARTEXT_BEGIN => {}
+        _ => return Err(SignatureError::MalformedCleartext)
+    }
+
+    let mut hash_headers = Vec::new();
+    loop {
+        match lines.next() {
+            Some((_, "")) => break,
+            Some((_, line)) => match line.strip_prefix("Hash:") {
+                Some(value) => hash_headers.push(String::from(value.trim())),
+                None => return Err(SignatureError::MalformedCleartext)
+            },
+            None => return Err(SignatureError::MalformedCleartext)
+        }
+    }
+
+    let mut signed_lines = Vec::new();
+    let signature_start = loop {
+        match lines.next() {
+            Some((start, line)) if line.trim_end() == SIGNATURE_BEGIN => break start,
+            Some((_, line)) => signed_lines.push(line.strip_prefix("- ").unwrap_or(line)),
+            None => return Err(SignatureError::MalformedCleartext)
+        }
+    };
+
+    let block = scanner::scan(&text[signature_start..]).next().ok_or(SignatureError::MalformedCleartext)?;
+    if block.span().start != 0 || *block.message().header_type() != MessageType::PGPSignature {
+        return Err(SignatureError::MalformedCleartext);
+    }
+
+    Ok(CleartextMessage {
+        hash_headers,
+        text: signed_lines.join("\n"),
+        signature: block.into_message()
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use lexer::Lexer;
+    use parser::Parser;
+    use key::{KeyId, PublicKeyAlgorithm};
+    use super::{HashAlgorithm, SignatureType, SignatureError};
+
+
+    // The self signature on the User ID of `sample/pub.asc`.
+    fn signature_block(hash: &str) -> String {
+        format!("-----BEGIN PGP SIGNATURE-----\n\
+                 Hash: {}\n\
+                 \n\
+                 iH8EExEKACcFAldfslkCGwMFCQeGH4AFCwkIBwMFFQoJCAsFFgIDAQACHgECF4AA\n\
+                 CgkQ3ii2VfLGWEoYNwEAjzlyXCNa94EECAjditP7DzWvycn/44A9DeQsb0w4/D8B\n\
+                 AN372eme1NHZ9Jm6j9L+P2yCkTLQtUXizaqa/KWdU+xK\n\
+                 =wiX9\n\
+                 -----END PGP SIGNATURE-----\n", hash)
+    }
+
+    #[test]
+    fn test_signature_info() {
+        let text = include_str!("../sample/pub.asc");
+        let message = Parser::new(Lexer::new(text)).parse().unwrap();
+        let signatures = super::message_signatures(&message).unwrap();
+        let issuer = KeyId::new([0xDE, 0x28, 0xB6, 0x55, 0xF2, 0xC6, 0x58, 0x4A]);
+
+        let summary: Vec<_> = signatures.iter()
+                                        .map(|signature| (signature.offset(), signature.signature_type()))
+                                        .collect();
+        assert_eq!(summary, vec![
+            (839, SignatureType::PositiveCertification),
+            (1496, SignatureType::SubkeyBinding)
+        ]);
+        for signature in signatures.iter() {
+            assert_eq!(signature.version(), 4);
+            assert_eq!(signature.public_key_algorithm(), PublicKeyAlgorithm::Dsa);
+            assert_eq!(signature.hash_algorithm(), HashAlgorithm::Sha512);
+            assert_eq!(signature.created(), Some(0x575F_B259));
+            assert_eq!(signature.issuer(), Some(issuer));
+            assert_eq!(signature.issuer_fingerprint(), None);
+        }
+    }
+
+    #[test]
+    fn test_check_signature_block() {
+        let results = vec![
+            ("SHA512", Ok(())),
+            ("SHA256, SHA512", Ok(())),
+            ("SHA256", Err(SignatureError::HashMismatch { offset: 0, algorithm: HashAlgorithm::Sha512 })),
+            ("SHA512, WHIRLPOOL", Err(SignatureError::UnknownHashHeader))
+        ];
+
+        for (hash, result) in results {
+            let text = signature_block(hash);
+            let message = Parser::new(Lexer::new(&text)).parse().unwrap();
+            assert_eq!(super::check_signature_block(&message).map(|_| ()), result);
+        }
+    }
+
+    #[test]
+    fn test_parse_cleartext() {
+        let text = format!("-----BEGIN PGP SIGNED MESSAGE-----\r\n\
+                            Hash: SHA256\r\n\
+                            \r\n\
+                            Hello,\r\n\
+                            - -- dashes\r\n\
+                            {}", signature_block("SHA512").replace("\n", "\r\n"));
+        let cleartext = super::parse_cleartext(&text).unwrap();
+
+        assert_eq!(cleartext.hash_headers(), &[String::from("SHA256")][..]);
+        assert_eq!(cleartext.text(), "Hello,\n-- dashes");
+        assert_eq!(cleartext.check(),
+                   Err(SignatureError::HashMismatch { offset: 0, algorithm: HashAlgorithm::Sha512 }));
+
+        let text = text.replace("Hash: SHA256", "Hash: SHA512");
+        assert_eq!(super::parse_cleartext(&text).unwrap().check().map(|signatures| signatures.len()), Ok(1));
+
+        let text = text.replace("Hash: SHA512", "Hash: sha512");
+        assert_eq!(super::parse_cleartext(&text).unwrap().check().map(|signatures| signatures.len()), Ok(1));
+
+        let text = text.replace("\r\n\r\nHello", "\r\nComment: x\r\n\r\nHello");
+        assert_eq!(super::parse_cleartext(&text), Err(SignatureError::MalformedCleartext));
+    }
+
+    #[test]
+    fn test_signature_errors() {
+        // A version 3 signature with a hashed material length of 4.
+        let data = [0xC2, 0x04, 0x03, 0x04, 0x00, 0x00];
+        assert_eq!(super::signatures(&data), Err(SignatureError::MalformedSignature));
+        let data = [0xC2, 0x02, 0x03, 0x05];
+        assert_eq!(super::signatures(&data), Err(SignatureError::TruncatedSignature));
+    }
+
+    #[test]
+    fn test_hash_algorithm_from_name() {
+        assert_eq!(HashAlgorithm::from_name("SHA256"), Some(HashAlgorithm::Sha256));
+        assert_eq!(HashAlgorithm::from_name("sha256"), Some(HashAlgorithm::Sha256));
+        assert_eq!(HashAlgorithm::from_name("WHIRLPOOL"), None);
+    }
+}
@@ -101,52 +101,45 @@ pub fn string_to_token_type(token_string: &str) -> Option<TokenType> {
     }
 }
 
+/// A byte range of the lexer input.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
-pub struct Location {
-    pub absolute: isize,
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
 }
 
-impl Location {
-    pub fn eof() -> Location {
-        Location { absolute: -1 }
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span {
+            start,
+            end,
+        }
     }
 
     #[inline]
-    pub fn increment(&mut self, amount: usize) {
-        self.absolute += amount as isize;
+    pub fn len(&self) -> usize {
+        self.end - self.start
     }
 
-    #[allow(dead_code)]
     #[inline]
-    pub fn decrement(&mut self, amount: usize) {
-        self.absolute -= amount as isize;
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+/// A token of the armor grammar. The token borrows nothing; its text is the
+/// span of the input it was scanned from.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct Token {
     token_type: TokenType,
-    text: String,
-    location: Location,
+    span: Span,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, text: &str, location: Location) -> Token {
+    pub fn new(token_type: TokenType, span: Span) -> Token {
         Token {
-            token_type: token_type,
-            text: String::from(text),
-            location: location,
-        }
-    }
-
-    pub fn from_char(token_type: TokenType, ch: char, location: Location) -> Token {
-        let mut text = String::new();
-        text.push(ch);
-
-        Token {
-            token_type: token_type,
-            text: text,
-            location: location
+            token_type,
+            span,
         }
     }
 
@@ -158,198 +151,51 @@ impl Token {
         self.token_type
     }
 
-    pub fn as_str(&self) -> &str {
-        self.text.as_str()
-    }
-
-    pub fn as_bytes(&self) -> &[u8] {
-        self.text.as_bytes()
-    }
-
-    fn is_pad(&self) -> bool {
-        (self.token_type == TokenType::Pad) && (self.text == "=")
-    }
-
-    fn is_forwardslash(&self) -> bool {
-        (self.token_type == TokenType::ForwardSlash) && (self.text == "/")
-    }
-
-    fn is_colon(&self) -> bool {
-        (self.token_type == TokenType::Colon) && (self.text == ":")
-    }
-
-    fn is_comma(&self) -> bool {
-        (self.token_type == TokenType::Comma) && (self.text == ",")
-    }
-
-    fn is_digit(&self) -> bool {
-        (self.token_type == TokenType::Digit) && DIGITS.contains(&self.text.as_str())
-    }
-
-    fn is_letter(&self) -> bool {
-        (self.token_type == TokenType::Letter) && LETTERS.contains(&self.text.as_str())
-    }
-
-    fn is_plussign(&self) -> bool {
-        (self.token_type == TokenType::PlusSign) && (self.text == "+")
-    }
-
-    fn is_whitespace(&self) -> bool {
-        (self.token_type == TokenType::WhiteSpace) && (self.text == " ")
-    }
-
-    fn is_other_utf8(&self) -> bool {
-        (self.token_type == TokenType::OtherUtf8) && (self.text.len() == 1)
-    }
-
-    fn is_colonspace(&self) -> bool {
-        (self.token_type == TokenType::ColonSpace) && (self.text == ": ")
-    }
-
-    fn is_newline(&self) -> bool {
-        (self.token_type == TokenType::NewLine) && (self.text == "\n" || self.text == "\r")
-    }
-
-    fn is_fivedashes(&self) -> bool {
-        (self.token_type == TokenType::FiveDashes) && (self.text == "-----")
-    }
-
-    fn is_begin(&self) -> bool {
-        (self.token_type == TokenType::Begin) && (self.text == "BEGIN ")
-    }
-
-    fn is_end(&self) -> bool {
-        (self.token_type == TokenType::End) && (self.text == "END ")
-    }
-
-    fn is_version(&self) -> bool {
-        (self.token_type == TokenType::Version) && (self.text == "Version")
+    pub fn span(&self) -> Span {
+        self.span
     }
 
-    fn is_comment(&self) -> bool {
-        (self.token_type == TokenType::Comment) && (self.text == "Comment")
-    }
-
-    fn is_messageid(&self) -> bool {
-        (self.token_type == TokenType::MessageID) && (self.text == "MessageID")
+    /// The text of the token in the input it was scanned from.
+    #[inline]
+    pub fn text<'a>(&self, input: &'a str) -> &'a str {
+        &input[self.span.start..self.span.end]
     }
 
-    fn is_hash(&self) -> bool {
-        (self.token_type == TokenType::Hash) && (self.text == "Hash")
+    pub fn is_valid_token(&self, input: &str) -> bool {
+        let text = self.text(input);
+        match self.token_type {
+            TokenType::Digit => DIGITS.contains(&text),
+            TokenType::Letter => LETTERS.contains(&text),
+            TokenType::OtherUtf8 => text.chars().count() == 1,
+            TokenType::NewLine => text == "\n" || text == "\r",
+            TokenType::BlankLine => is_blankline(text),
+            // The end of input token spans no text.
+            TokenType::Eof => text.is_empty(),
+            token_type => token_type.armor_string() == Some(text)
+        }
     }
+}
 
-    fn is_charset(&self) -> bool {
-        (self.token_type == TokenType::Charset) && (self.text == "Charset")
+fn is_blankline(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some('\n') | Some('\r') => {}
+        _ => return false
     }
 
-    fn is_blankline(&self) -> bool {
-        let mut chars = self.text.chars();
+    loop {
         match chars.next() {
-            Some('\n') | Some('\r') => {}
+            Some(' ') => continue,
+            Some('\n') | Some('\r') => break,
             _ => return false
         }
-
-        loop {
-            match chars.next() {
-                Some(' ') => continue,
-                Some('\n') | Some('\r') => break,
-                _ => return false
-            }
-        }
-
-        match chars.next() {
-            Some(_) => return false,
-            None    => {}
-        }
-
-        self.token_type == TokenType::BlankLine
     }
 
-    fn is_pgp_message(&self) -> bool {
-        (self.token_type == TokenType::PGPMessage) && (self.text == "PGP MESSAGE")
-    }
-
-    fn is_pgp_publickey_block(&self) -> bool {
-        (self.token_type == TokenType::PGPPublicKeyBlock) && (self.text == "PGP PUBLIC KEY BLOCK")
-    }
-
-    fn is_pgp_privatekey_block(&self) -> bool {
-        (self.token_type == TokenType::PGPPublicKeyBlock) && (self.text == "PGP PRIVATE KEY BLOCK")
-    }
-
-    fn is_pgp_message_part(&self) -> bool {
-        (self.token_type == TokenType::PGPMessagePart) && (self.text == "PGP MESSAGE, PART ")
-    }
-
-    fn is_pgp_signature(&self) -> bool {
-        (self.token_type == TokenType::PGPSignature) && (self.text == "PGP SIGNATURE")
-    }
-
-    fn is_eof(&self) -> bool {
-        (self.token_type == TokenType::Eof) && (self.text == "EOF")
-    }
-
-    pub fn is_valid_token(&self) -> bool {
-        match self.token_type {
-            TokenType::Pad => self.is_pad(),
-            TokenType::ForwardSlash => self.is_forwardslash(),
-            TokenType::Colon => self.is_colon(),
-            TokenType::Comma => self.is_comma(),
-            TokenType::Digit => self.is_digit(),
-            TokenType::Letter => self.is_letter(),
-            TokenType::PlusSign => self.is_plussign(),
-            TokenType::WhiteSpace => self.is_whitespace(),
-            TokenType::OtherUtf8 => self.is_other_utf8(),
-            TokenType::ColonSpace => self.is_colonspace(),
-            TokenType::NewLine => self.is_newline(),
-            TokenType::FiveDashes => self.is_fivedashes(),
-            TokenType::Begin => self.is_begin(),
-            TokenType::End => self.is_end(),
-            TokenType::Version => self.is_version(),
-            TokenType::Comment => self.is_comment(),
-            TokenType::MessageID => self.is_messageid(),
-            TokenType::Hash => self.is_hash(),
-            TokenType::Charset => self.is_charset(),
-            TokenType::BlankLine => self.is_blankline(),
-            TokenType::PGPMessage => self.is_pgp_message(),
-            TokenType::PGPPublicKeyBlock => self.is_pgp_publickey_block(),
-            TokenType::PGPPrivateKeyBlock => self.is_pgp_privatekey_block(),
-            TokenType::PGPMessagePart => self.is_pgp_message_part(),
-            TokenType::PGPSignature => self.is_pgp_signature(),
-            TokenType::Eof => self.is_eof()
-        }
-    }
+    chars.next().is_none()
 }
 
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self.token_type {
-            TokenType::Pad => write!(f, "Pad(\"{}\")", self.text),
-            TokenType::ForwardSlash => write!(f, "ForwardSlash(\"{}\")", self.text),
-            TokenType::Colon => write!(f, "Colon(\"{}\")", self.text),
-            TokenType::Comma => write!(f, "Comma(\"{}\")", self.text),
-            TokenType::Digit => write!(f, "Digit(\"{}\")", self.text),
-            TokenType::Letter => write!(f, "Letter(\"{}\")", self.text),
-            TokenType::PlusSign => write!(f, "PlusSign(\"{}\")", self.text),
-            TokenType::WhiteSpace => write!(f, "WhiteSpace(\"{}\")", self.text),
-            TokenType::OtherUtf8 => write!(f, "OtherUtf8(\"{}\")", self.text),
-            TokenType::ColonSpace => write!(f, "ColonSpace(\"{}\")", self.text),
-            TokenType::NewLine => write!(f, "NewLine(\"{}\")", self.text),
-            TokenType::FiveDashes => write!(f, "FiveDashes(\"{}\")", self.text),
-            TokenType::Begin => write!(f, "Begin(\"{}\")", self.text),
-            TokenType::End => write!(f, "End(\"{}\")", self.text),
-            TokenType::Version => write!(f, "Version(\"{}\")", self.text),
-            TokenType::Comment => write!(f, "Comment(\"{}\")", self.text),
-            TokenType::MessageID => write!(f, "MessageID(\"{}\")", self.text),
-            TokenType::Hash => write!(f, "Hash(\"{}\")", self.text),
-            TokenType::Charset => write!(f, "Charset(\"{}\")", self.text),
-            TokenType::BlankLine => write!(f, "BlankLine(\"{}\")", self.text),
-            TokenType::PGPMessage => write!(f, "PGPMessage(\"{}\")", self.text),
-            TokenType::PGPPublicKeyBlock => write!(f, "PGPPublicKeyBlock(\"{}\")", self.text),
-            TokenType::PGPPrivateKeyBlock => write!(f, "PGPPrivateKeyBlock(\"{}\")", self.text),
-            TokenType::PGPMessagePart => write!(f, "PGPMessagePart(\"{}\")", self.text),
-            TokenType::PGPSignature => write!(f, "PGPSignature(\"{}\")", self.text),
-            TokenType::Eof => write!(f, "EOF(\"{}\")", self.text)
-        }
+        write!(f, "{:?}({}..{})", self.token_type, self.span.start, self.span.end)
     }
 }
@@ -0,0 +1,96 @@
+use key::{KeyError, KeyResult};
+use packet::{self, PacketTag};
+use parser::ArmorMessage;
+
+
+/// A user packet of a key block: a User ID or a User Attribute.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum UserEntry {
+    /// A User ID, conventionally an RFC2822 name and address. Invalid UTF-8
+    /// sequences are replaced with U+FFFD.
+    UserId { offset: usize, id: String },
+    /// A User Attribute, usually a photo. The subpackets are left as they are.
+    UserAttribute { offset: usize, data: Vec<u8> }
+}
+
+impl UserEntry {
+    /// The offset of the user packet in the packet stream.
+    pub fn offset(&self) -> usize {
+        match *self {
+            UserEntry::UserId { offset, .. } => offset,
+            UserEntry::UserAttribute { offset, .. } => offset
+        }
+    }
+
+    pub fn user_id(&self) -> Option<&str> {
+        match *self {
+            UserEntry::UserId { ref id, .. } => Some(id),
+            UserEntry::UserAttribute { .. } => None
+        }
+    }
+}
+
+/// The User ID and User Attribute packets in a packet stream, in stream order.
+pub fn users(data: &[u8]) -> KeyResult<Vec<UserEntry>> {
+    let mut users = Vec::new();
+    for packet in packet::packets(data) {
+        let packet = packet.map_err(KeyError::MalformedPacket)?;
+        match packet.tag() {
+            PacketTag::UserID => {
+                let id = String::from_utf8_lossy(packet.body()).into_owned();
+                users.push(UserEntry::UserId { offset: packet.offset(), id });
+            }
+            PacketTag::UserAttribute => {
+                let offset = packet.offset();
+                users.push(UserEntry::UserAttribute { offset, data: packet.into_body().into_owned() });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(users)
+}
+
+/// The User ID and User Attribute packets in the body of an armor message.
+pub fn message_users(message: &ArmorMessage) -> KeyResult<Vec<UserEntry>> {
+    let data = message.decode_secret_body().ok_or(KeyError::CorruptBody)?;
+    users(&data)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use lexer::Lexer;
+    use parser::Parser;
+    use super::UserEntry;
+
+
+    #[test]
+    fn test_users() {
+        let data = vec![
+            0xC6, 0x01, 0x04,
+            0xCD, 0x05, b'a', b'l', b'i', b'c', b'e',
+            0xD1, 0x03, 0x02, 0x01, 0x00,
+            0xB4, 0x02, 0xC3, 0x28
+        ];
+        let users = super::users(&data).unwrap();
+
+        assert_eq!(users, vec![
+            UserEntry::UserId { offset: 3, id: String::from("alice") },
+            UserEntry::UserAttribute { offset: 10, data: vec![0x02, 0x01, 0x00] },
+            UserEntry::UserId { offset: 15, id: String::from("\u{FFFD}(") }
+        ]);
+        assert_eq!(users[1].user_id(), None);
+    }
+
+    #[test]
+    fn test_sample_users() {
+        for text in [include_str!("../sample/pub.asc"), include_str!("../sample/priv.asc")].iter() {
+            let message = Parser::new(Lexer::new(text)).parse().unwrap();
+            let users = super::message_users(&message).unwrap();
+
+            assert_eq!(users.len(), 1);
+            assert_eq!(users[0].user_id(), Some("Foobar <foo@foo.bar>"));
+        }
+    }
+}
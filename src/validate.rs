@@ -0,0 +1,251 @@
+use std::cmp;
+use std::str;
+use base64;
+use crc24::{Crc24, Crc24Hasher};
+use lexer::Lexer;
+use parser::{self, Parser, MessageType, HeaderType, ParseError, ParseResult};
+use visitor::{Visitor, Location};
+use zeroize;
+
+
+// A full body line of 76 base64 characters decodes to 57 octets.
+const LINE_OCTETS: usize = 57;
+
+
+/// What validating an armor block found out about it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Summary {
+    message_type: MessageType,
+    header_count: usize,
+    decoded_length: usize,
+    checksum: Crc24,
+    valid_checksum: bool
+}
+
+impl Summary {
+    pub fn message_type(&self) -> &MessageType {
+        &self.message_type
+    }
+
+    pub fn header_count(&self) -> usize {
+        self.header_count
+    }
+
+    /// The number of octets the body decodes to.
+    pub fn decoded_length(&self) -> usize {
+        self.decoded_length
+    }
+
+    /// The CRC-24 of the decoded body.
+    pub fn checksum(&self) -> Crc24 {
+        self.checksum
+    }
+
+    /// Tests whether the armor checksum matches the CRC-24 of the decoded body.
+    pub fn has_valid_checksum(&self) -> bool {
+        self.valid_checksum
+    }
+}
+
+// Decodes the body a line at a time into a buffer the size of one line,
+// keeping only its length and CRC-24.
+struct Validator {
+    message_type: Option<MessageType>,
+    header_count: usize,
+    group: [u8; 4],
+    group_length: usize,
+    padded: bool,
+    octets: Vec<u8>,
+    hasher: Crc24Hasher,
+    decoded_length: usize,
+    valid_checksum: bool,
+    error: Option<ParseError>
+}
+
+impl Validator {
+    fn new() -> Validator {
+        Validator {
+            message_type: None,
+            header_count: 0,
+            group: [0; 4],
+            group_length: 0,
+            padded: false,
+            octets: Vec::with_capacity(LINE_OCTETS),
+            hasher: Crc24Hasher::new(),
+            decoded_length: 0,
+            valid_checksum: false,
+            error: None
+        }
+    }
+
+    fn decode_groups(&mut self, groups: &[u8]) -> ParseResult<()> {
+        // Padding may only end the body.
+        if self.padded {
+            return Err(ParseError::CorruptBody);
+        }
+        self.padded = groups.last() == Some(&b'=');
+
+        let decoded = base64::decode_bytes_into(groups, &mut self.octets);
+        self.hasher.update(&self.octets);
+        self.decoded_length += self.octets.len();
+        zeroize::zeroize(&mut self.octets);
+        self.octets.clear();
+
+        decoded.ok_or(ParseError::CorruptBody)
+    }
+
+    fn decode_line(&mut self, line: &[u8]) -> ParseResult<()> {
+        let mut rest = line;
+        if self.group_length > 0 {
+            let length = cmp::min(4 - self.group_length, rest.len());
+            self.group[self.group_length..self.group_length + length].copy_from_slice(&rest[..length]);
+            self.group_length += length;
+            rest = &rest[length..];
+            if self.group_length < 4 {
+                return Ok(());
+            }
+            self.group_length = 0;
+            let group = self.group;
+            self.decode_groups(&group)?;
+        }
+
+        let whole = rest.len() / 4 * 4;
+        if whole > 0 {
+            self.decode_groups(&rest[..whole])?;
+        }
+
+        let tail = &rest[whole..];
+        if !tail.is_empty() {
+            if self.padded {
+                return Err(ParseError::CorruptBody);
+            }
+            self.group[..tail.len()].copy_from_slice(tail);
+            self.group_length = tail.len();
+        }
+
+        Ok(())
+    }
+}
+
+impl Visitor for Validator {
+    fn on_begin(&mut self, message_type: &MessageType) {
+        self.message_type = Some(message_type.clone());
+    }
+
+    fn on_header(&mut self, _header_type: &HeaderType, _value: &str) {
+        self.header_count += 1;
+    }
+
+    fn on_body_line(&mut self, line: &str, _location: Location) {
+        if self.error.is_none() {
+            self.error = self.decode_line(line.as_bytes()).err();
+        }
+    }
+
+    fn on_checksum(&mut self, checksum: &str) {
+        if self.error.is_none() && self.group_length > 0 {
+            self.error = Some(ParseError::CorruptBody);
+        }
+        self.valid_checksum = parser::decode_checksum(checksum) == Some(self.hasher.finish());
+    }
+}
+
+impl Drop for Validator {
+    fn drop(&mut self) {
+        zeroize::zeroize(&mut self.group);
+    }
+}
+
+/// Check that the input is well-formed armor whose body is valid base64,
+/// without keeping the decoded body. The body is decoded a line at a time to
+/// find its length and CRC-24, so checking a block costs no more memory than
+/// one of its lines.
+///
+/// Armor is ASCII, so the input is only read up to its first byte that is not
+/// valid UTF-8.
+pub fn validate(input: &[u8]) -> ParseResult<Summary> {
+    let text = match str::from_utf8(input) {
+        Ok(text) => text,
+        Err(err) => str::from_utf8(&input[..err.valid_up_to()]).map_err(|_| ParseError::ParseError)?
+    };
+
+    let mut validator = Validator::new();
+    let result = Parser::new(Lexer::new(text)).visit(&mut validator);
+    // A body error comes before anything the parser finds after the body.
+    if let Some(err) = validator.error {
+        return Err(err);
+    }
+    result?;
+
+    Ok(Summary {
+        message_type: validator.message_type.take().ok_or(ParseError::ParseError)?,
+        header_count: validator.header_count,
+        decoded_length: validator.decoded_length,
+        checksum: validator.hasher.finish(),
+        valid_checksum: validator.valid_checksum
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use lexer::Lexer;
+    use parser::{Parser, MessageType, ParseError};
+    use super::validate;
+
+
+    #[test]
+    fn test_validate() {
+        for text in [include_str!("../sample/message.asc"),
+                     include_str!("../sample/pub.asc"),
+                     include_str!("../sample/priv.asc")].iter() {
+            let message = Parser::new(Lexer::new(text)).parse().unwrap();
+            let body = message.decode_body().unwrap();
+            let summary = validate(text.as_bytes()).unwrap();
+
+            assert_eq!(summary.message_type(), message.header_type());
+            assert_eq!(summary.header_count(), message.header_block().len());
+            assert_eq!(summary.decoded_length(), body.len());
+            assert_eq!(summary.checksum(), ::crc24::crc_octets(&body));
+            assert!(summary.has_valid_checksum());
+        }
+    }
+
+    #[test]
+    fn test_validate_checksum_mismatch() {
+        let text = include_str!("../sample/message.asc").replace("=njUN", "=njUM");
+        let summary = validate(text.as_bytes()).unwrap();
+
+        assert_eq!(summary.message_type(), &MessageType::PGPMessage);
+        assert!(!summary.has_valid_checksum());
+    }
+
+    #[test]
+    fn test_validate_groups_across_lines() {
+        let text = "-----BEGIN PGP MESSAGE-----\n\nZm9vY\nmFy\n=GkBC\n-----END PGP MESSAGE-----\n";
+        let summary = validate(text.as_bytes()).unwrap();
+
+        assert_eq!(summary.decoded_length(), 6);
+        assert_eq!(summary.checksum(), ::crc24::crc_octets(b"foobar"));
+    }
+
+    #[test]
+    fn test_validate_errors() {
+        let tests = [
+            ("-----BEGIN PGP MESSAGE-----\n\nZm9\n=wRlL\n-----END PGP MESSAGE-----\n", ParseError::CorruptBody),
+            ("-----BEGIN PGP MESSAGE-----\n\nZg==\nZm9v\n=wRlL\n-----END PGP MESSAGE-----\n", ParseError::CorruptBody),
+            ("-----BEGIN PGP MESSAGE-----\n\nZm9v\n=wRlL\n-----END PGP SIGNATURE-----\n", ParseError::ParseError),
+            ("-----BEGIN PGP MESSAGE-----\n\nZm9v\n", ParseError::EndOfFile)
+        ];
+        for &(text, err) in tests.iter() {
+            assert_eq!(validate(text.as_bytes()), Err(err));
+        }
+
+        let mut octets = include_str!("../sample/message.asc").as_bytes().to_vec();
+        octets[60] = 0xff;
+        assert!(validate(&octets).is_err());
+        octets = include_str!("../sample/message.asc").as_bytes().to_vec();
+        octets.push(0xff);
+        assert!(validate(&octets).is_ok());
+    }
+}
@@ -0,0 +1,131 @@
+use parser::{MessageType, HeaderType};
+
+
+/// Where a line starts in the armor: its 1-based line number, and its byte
+/// offset in the input.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Location {
+    pub line: usize,
+    pub offset: usize
+}
+
+/// Observes the structure of an armor block as `Parser::visit` parses it.
+/// The methods are called in the order the parts appear in the armor, and do
+/// nothing unless overridden.
+pub trait Visitor {
+    fn on_begin(&mut self, _message_type: &MessageType) {}
+
+    fn on_header(&mut self, _header_type: &HeaderType, _value: &str) {}
+
+    /// A line of base64 body data, without its line ending.
+    fn on_body_line(&mut self, _line: &str, _location: Location) {}
+
+    /// The checksum, as the four base64 characters after the `=`.
+    fn on_checksum(&mut self, _checksum: &str) {}
+
+    fn on_end(&mut self, _message_type: &MessageType) {}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use lexer::Lexer;
+    use parser::{Parser, MessageType, HeaderType, ParseError};
+    use super::{Visitor, Location};
+
+
+    #[derive(Debug, PartialEq)]
+    enum Visit {
+        Begin(MessageType),
+        Header(HeaderType, String),
+        BodyLine(String, Location),
+        Checksum(String),
+        End(MessageType)
+    }
+
+    struct Recorder {
+        visits: Vec<Visit>
+    }
+
+    impl Visitor for Recorder {
+        fn on_begin(&mut self, message_type: &MessageType) {
+            self.visits.push(Visit::Begin(message_type.clone()));
+        }
+
+        fn on_header(&mut self, header_type: &HeaderType, value: &str) {
+            self.visits.push(Visit::Header(header_type.clone(), String::from(value)));
+        }
+
+        fn on_body_line(&mut self, line: &str, location: Location) {
+            self.visits.push(Visit::BodyLine(String::from(line), location));
+        }
+
+        fn on_checksum(&mut self, checksum: &str) {
+            self.visits.push(Visit::Checksum(String::from(checksum)));
+        }
+
+        fn on_end(&mut self, message_type: &MessageType) {
+            self.visits.push(Visit::End(message_type.clone()));
+        }
+    }
+
+    fn visit(text: &str) -> (Vec<Visit>, Result<(), ParseError>) {
+        let mut recorder = Recorder { visits: Vec::new() };
+        let result = Parser::new(Lexer::new(text)).visit(&mut recorder);
+
+        (recorder.visits, result)
+    }
+
+    #[test]
+    fn test_visit_message() {
+        let text = include_str!("../sample/message.asc");
+        let first = "yDgBO22WxBHv7O8X7O/jygAEzol56iUKiXmV+XmpCtmpqQUKiQrFqclFqUDBovzS";
+        let second = "vBSFjNSiVHsuAA==";
+        let (visits, result) = visit(text);
+
+        assert!(result.is_ok());
+        assert_eq!(visits, vec![
+            Visit::Begin(MessageType::PGPMessage),
+            Visit::Header(HeaderType::Version, String::from("OpenPrivacy 0.99")),
+            Visit::BodyLine(String::from(first), Location { line: 4, offset: text.find(first).unwrap() }),
+            Visit::BodyLine(String::from(second), Location { line: 5, offset: text.find(second).unwrap() }),
+            Visit::Checksum(String::from("njUN")),
+            Visit::End(MessageType::PGPMessage)
+        ]);
+    }
+
+    #[test]
+    fn test_visit_matches_parse() {
+        // An empty line before the body takes the parser off its fast path,
+        // so both ways of reading the body are visited.
+        let text = include_str!("../sample/pub.asc");
+        let spaced = text.replacen("\n\n", "\n\n\n", 1);
+        for text in [text, &spaced[..]].iter() {
+            let message = Parser::new(Lexer::new(text)).parse().unwrap();
+            let (visits, result) = visit(text);
+            assert!(result.is_ok());
+
+            let mut body = String::new();
+            let mut last_line = 0;
+            for visit in visits.iter() {
+                if let Visit::BodyLine(ref line, location) = *visit {
+                    assert!(text[location.offset..].starts_with(&line[..]));
+                    assert_eq!(location.line, text[..location.offset].matches('\n').count() + 1);
+                    assert!(location.line > last_line);
+                    last_line = location.line;
+                    body.push_str(line);
+                }
+            }
+            assert_eq!(body, message.body());
+        }
+    }
+
+    #[test]
+    fn test_visit_mismatched_tail() {
+        let text = include_str!("../sample/message.asc").replace("END PGP MESSAGE", "END PGP SIGNATURE");
+        let (visits, result) = visit(&text);
+
+        assert_eq!(result, Err(ParseError::ParseError));
+        assert_eq!(visits.last(), Some(&Visit::Checksum(String::from("njUN"))));
+    }
+}
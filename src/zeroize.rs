@@ -0,0 +1,134 @@
+use std::cmp;
+use std::fmt;
+use std::ops::Deref;
+use std::ptr;
+use std::sync::atomic::{self, Ordering};
+
+
+/// Overwrite a buffer with zeros. The writes are volatile so the compiler
+/// cannot drop them as dead stores before the buffer is freed.
+pub fn zeroize(octets: &mut [u8]) {
+    for octet in octets.iter_mut() {
+        // The pointer comes from a mutable reference, so it is valid and aligned.
+        unsafe { ptr::write_volatile(octet, 0) };
+    }
+    atomic::compiler_fence(Ordering::SeqCst);
+}
+
+/// Overwrite a string with zeros and empty it.
+pub fn zeroize_string(string: &mut String) {
+    // A run of NUL characters is valid UTF-8.
+    unsafe { zeroize(string.as_bytes_mut()) };
+    string.clear();
+}
+
+/// Append to a string that holds secret data. When the string has to grow,
+/// the old allocation is wiped instead of being freed with its contents.
+pub fn push_str(buffer: &mut String, text: &str) {
+    if buffer.capacity() - buffer.len() < text.len() {
+        let capacity = cmp::max(buffer.capacity() * 2, buffer.len() + text.len());
+        let mut grown = String::with_capacity(capacity);
+        grown.push_str(buffer);
+        zeroize_string(buffer);
+        *buffer = grown;
+    }
+    buffer.push_str(text);
+}
+
+/// Append to a byte buffer that holds secret data, wiping the old allocation
+/// when the buffer has to grow.
+pub fn push_bytes(buffer: &mut Vec<u8>, octets: &[u8]) {
+    if buffer.capacity() - buffer.len() < octets.len() {
+        let capacity = cmp::max(buffer.capacity() * 2, buffer.len() + octets.len());
+        let mut grown = Vec::with_capacity(capacity);
+        grown.extend_from_slice(buffer);
+        zeroize(buffer);
+        *buffer = grown;
+    }
+    buffer.extend_from_slice(octets);
+}
+
+/// A buffer of secret octets, such as the decoded body of a private key
+/// block. The buffer is wiped when it is dropped and its `Debug` output does
+/// not show the contents.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretBytes {
+    data: Vec<u8>
+}
+
+impl SecretBytes {
+    /// Take ownership of a buffer. The buffer should not have been reallocated
+    /// on its way here, since reallocation leaves copies behind.
+    pub fn new(data: Vec<u8>) -> SecretBytes {
+        SecretBytes { data }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Deref for SecretBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        zeroize(&mut self.data);
+    }
+}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretBytes([REDACTED; {}])", self.data.len())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::SecretBytes;
+
+
+    #[test]
+    fn test_zeroize() {
+        let mut octets = vec![1, 2, 3];
+        super::zeroize(&mut octets);
+        assert_eq!(octets, vec![0, 0, 0]);
+
+        let mut string = String::from("secret");
+        super::zeroize_string(&mut string);
+        assert!(string.is_empty());
+    }
+
+    #[test]
+    fn test_push_str() {
+        let mut buffer = String::with_capacity(2);
+        for piece in ["ab", "cde", "f", "ghijklmnop"].iter() {
+            super::push_str(&mut buffer, piece);
+        }
+
+        assert_eq!(buffer, "abcdefghijklmnop");
+    }
+
+    #[test]
+    fn test_push_bytes() {
+        let mut buffer = Vec::with_capacity(2);
+        for piece in [&b"ab"[..], b"cde", b"", b"fghij"].iter() {
+            super::push_bytes(&mut buffer, piece);
+        }
+
+        assert_eq!(buffer, b"abcdefghij");
+    }
+
+    #[test]
+    fn test_secret_bytes_debug() {
+        let secret = SecretBytes::new(vec![0x95, 0x01, 0x02]);
+        assert_eq!(&secret[..], &[0x95, 0x01, 0x02]);
+        assert_eq!(format!("{:?}", secret), "SecretBytes([REDACTED; 3])");
+    }
+}